@@ -3,7 +3,7 @@ use crossterm::{
     style::{Color, Print, ResetColor, SetBackgroundColor, SetForegroundColor},
 };
 use kno3_chess_engine::position::rank_file_to_square;
-use kno3_chess_engine::Chessboard;
+use kno3_chess_engine::{Chessboard, Piece};
 use std::io::stdout;
 
 #[rustfmt::skip]
@@ -14,7 +14,7 @@ fn find_fg(p: char) -> Color {
 
 #[rustfmt::skip]
 fn find_bkgnd(rank: u8, file: u8) -> Color {
-    if (rank + file) % 2 == 0
+    if (rank + file).is_multiple_of(2)
         { Color::Rgb { r: 255, g: 206, b: 158 } }
     else
         { Color::Rgb { r: 190, g: 140, b: 170 } }
@@ -33,7 +33,7 @@ impl DisplayBoard for Chessboard {
             print!("{rank} ");
             for (f_index, file) in files.iter().enumerate() {
                 let square = rank_file_to_square(*rank, *file).expect("These are manually set");
-                let piece = self.piece_at_position(square).unwrap_or('.');
+                let piece = self.piece_at_position(square).map_or('.', Piece::to_char);
 
                 let fg = find_fg(piece);
                 let frmt = format!("{:^3}", piece);
@@ -1,10 +1,17 @@
 use crate::display::DisplayBoard;
 mod display;
-use clap::{Arg, Command};
+mod uci;
+mod xboard;
+use clap::{Arg, ArgMatches, Command};
 use kno3_chess_engine::position;
+use kno3_chess_engine::Error as EngineError;
 use kno3_chess_engine::GameState;
+use kno3_chess_engine::PawnTable;
 use std::fmt;
 
+pub(crate) const ENGINE_NAME: &str = "KnO3";
+pub(crate) const DEFAULT_DEPTH: u32 = 4;
+
 #[derive(Debug)]
 enum Error {
     FENParsingError(String),
@@ -50,18 +57,77 @@ fn main() -> Result<(), Error> {
             Arg::new("evaluate")
                 .long("evaluate")
                 .short('e')
-                .help("Determines who is winning. Positive number indicates a white advantage.")
+                .help("Determines who is winning in centipawns. Positive number indicates a white advantage.")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("best-move")
+                .long("best-move")
+                .short('b')
+                .help("Search for the best move and print it with its score and principal variation")
                 .action(clap::ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("depth")
+                .long("depth")
+                .short('d')
+                .value_name("PLIES")
+                .help("How many plies deep --best-move searches (default: 4)")
+                .value_parser(clap::value_parser!(u32)),
+        )
         .arg(
             Arg::new("move")
                 .long("move")
                 .short('m')
                 .value_name("move")
-                .help("Move a piece (ex: 'E2:E4'"),
+                .help("Move a piece and print the new FEN (ex: 'E2:E4', 'E7:E8=Q' or 'e7e8q'). Repeat to chain moves")
+                .action(clap::ArgAction::Append),
+        )
+        .subcommand_negates_reqs(true)
+        .subcommand(
+            Command::new("perft")
+                .about("Count the positions reachable in DEPTH moves to validate move generation")
+                .arg(
+                    Arg::new("depth")
+                        .value_name("DEPTH")
+                        .required(true)
+                        .value_parser(clap::value_parser!(u32)),
+                )
+                .arg(
+                    Arg::new("fen")
+                        .short('f')
+                        .long("fen")
+                        .value_name("FEN")
+                        .help("FEN string to count from, defaults to the starting position"),
+                )
+                .arg(
+                    Arg::new("divide")
+                        .long("divide")
+                        .short('d')
+                        .help("Print the count below each first move")
+                        .action(clap::ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(Command::new("uci").about("Talk to a chess GUI over the UCI protocol"))
+        .subcommand(
+            Command::new("xboard")
+                .about("Talk to a chess GUI over the XBoard (CECP) protocol"),
         )
         .get_matches();
 
+    match matches.subcommand() {
+        Some(("perft", perft_matches)) => return perft(perft_matches),
+        Some(("uci", _)) => {
+            uci::run();
+            return Ok(());
+        }
+        Some(("xboard", _)) => {
+            xboard::run();
+            return Ok(());
+        }
+        _ => (),
+    }
+
     // Happen every time //
 
     let fen = matches
@@ -72,9 +138,14 @@ fn main() -> Result<(), Error> {
 
     // Setters //
 
-    match matches.get_one::<String>("move") {
-        None => (),
-        Some(coords) => move_piece(coords, &mut gs).map_err(Error::ArgumentError)?,
+    if let Some(moves) = matches.get_many::<String>("move") {
+        for coords in moves {
+            move_piece(coords, &mut gs).map_err(|e| Error::ArgumentError(e.to_string()))?;
+        }
+        println!("{gs}");
+        if let Some(result) = gs.result() {
+            println!("{result}");
+        }
     }
 
     // Getters //
@@ -83,40 +154,74 @@ fn main() -> Result<(), Error> {
         gs.board.display();
     }
     if matches.get_flag("evaluate") {
-        println!("{}", gs.board.evaluate());
+        println!("{}", gs.evaluate(&mut PawnTable::default()));
     }
     if let Some(position) = matches.get_one::<String>("get-moves") {
         let square = position::string_to_square(position)
             .map_err(|e| Error::ArgumentError(e.to_string()))?;
-        let moves = position::active_squares(gs.possible_moves(square))
+        let moves = gs
+            .possible_move_list(square)
             .into_iter()
-            .map(position::square_to_string)
+            .map(|mv| match mv.promotion {
+                None => position::square_to_string(mv.to),
+                Some(p) => format!(
+                    "{}={}",
+                    position::square_to_string(mv.to),
+                    p.to_char().to_ascii_uppercase()
+                ),
+            })
             .collect::<Vec<String>>()
             .join(" ");
         println!("{}", moves);
     }
+    if matches.get_flag("best-move") {
+        let depth = matches
+            .get_one::<u32>("depth")
+            .copied()
+            .unwrap_or(DEFAULT_DEPTH);
+        let result = gs.search(depth);
+        let pv = result
+            .pv
+            .iter()
+            .map(|mv| mv.to_string())
+            .collect::<Vec<String>>()
+            .join(" ");
+        match result.best_move {
+            None => println!("none"),
+            Some(mv) => println!("{mv} score {} pv {pv}", result.score),
+        }
+    }
 
     Ok(())
 }
 
-fn move_piece(move_string: &str, game: &mut GameState) -> Result<(), String> {
-    let mut coords = move_string.split(':');
+fn move_piece(move_string: &str, game: &mut GameState) -> Result<(), EngineError> {
+    let (from, to, promotion) = position::string_to_move(move_string)?;
+    game.move_piece_legally(from, to, promotion)
+}
 
-    let from = match coords.next() {
-        None => return Err("Invalid move format. Start position not supplied".to_string()),
-        Some(coord) => match position::string_to_square(coord) {
-            Err(e) => return Err(e),
-            Ok(square) => square,
-        },
+fn perft(matches: &ArgMatches) -> Result<(), Error> {
+    let depth = *matches
+        .get_one::<u32>("depth")
+        .ok_or(Error::ArgumentError("Depth required".to_string()))?;
+    let mut gs = match matches.get_one::<String>("fen") {
+        None => GameState::new(),
+        Some(fen) => {
+            GameState::from_string(fen).map_err(|e| Error::FENParsingError(e.to_string()))?
+        }
     };
 
-    let to = match coords.next() {
-        None => return Err("Invalid move format. End position not supplied".to_string()),
-        Some(coord) => match position::string_to_square(coord) {
-            Err(e) => return Err(e),
-            Ok(square) => square,
-        },
+    let nodes = if matches.get_flag("divide") {
+        let divided = gs.divide(depth);
+        for (mv, count) in &divided {
+            println!("{mv}: {count}");
+        }
+        println!();
+        divided.iter().map(|(_, count)| count).sum()
+    } else {
+        gs.perft(depth)
     };
 
-    game.move_piece_legally(from, to)
+    println!("Nodes searched: {nodes}");
+    Ok(())
 }
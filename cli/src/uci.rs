@@ -0,0 +1,404 @@
+use crate::{DEFAULT_DEPTH, ENGINE_NAME};
+use kno3_chess_engine::{
+    position, GameState, PawnTable, SearchLimits, SearchResult, TranspositionTable,
+    DEFAULT_HASH_MB, MATE, MAX_HASH_MB,
+};
+use std::io::{self, BufRead};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+// http://wbec-ridderkerk.nl/html/UCIProtocol.html
+
+const ENGINE_AUTHORS: &str = "Cooper Hanson, Abrahim Toutoungi";
+
+/// A search running in the background so `stop` and `isready` are still answered
+struct Search {
+    handle: JoinHandle<()>,
+    stop: Arc<AtomicBool>,
+}
+
+impl Search {
+    fn start(
+        mut game: GameState,
+        limits: SearchLimits,
+        table: Arc<Mutex<TranspositionTable>>,
+        pawns: Arc<Mutex<PawnTable>>,
+    ) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = Arc::clone(&stop);
+        let handle = thread::spawn(move || {
+            let mut table = table.lock().expect("Search thread panicked");
+            let mut pawns = pawns.lock().expect("Search thread panicked");
+            let result =
+                game.search_with_limits(&limits, &mut table, &mut pawns, &thread_stop, |r| {
+                    println!("{}", info(r))
+                });
+            // `go infinite` only answers after `stop`, even if the search ended early
+            while limits.infinite && !thread_stop.load(Ordering::Relaxed) {
+                thread::park();
+            }
+            match result.best_move {
+                Some(mv) => println!("bestmove {mv}"),
+                None => println!("bestmove 0000"), // no legal moves
+            }
+        });
+        Self { handle, stop }
+    }
+
+    /// Stop searching and wait for the best move to be printed
+    fn finish(self) {
+        self.stop.store(true, Ordering::Relaxed);
+        self.handle.thread().unpark();
+        let _ = self.handle.join();
+    }
+}
+
+/// Read UCI commands from stdin until `quit`
+pub fn run() {
+    let mut game = GameState::new();
+    let mut search: Option<Search> = None;
+    // Shared with the search thread, only touched here while no search is running
+    let table = Arc::new(Mutex::new(TranspositionTable::default()));
+    let pawns = Arc::new(Mutex::new(PawnTable::default()));
+
+    for line in io::stdin().lock().lines() {
+        let line = match line {
+            Ok(l) => l,
+            Err(_) => break,
+        };
+        let mut tokens = line.split_whitespace();
+
+        match tokens.next() {
+            Some("uci") => {
+                println!("id name {ENGINE_NAME}");
+                println!("id author {ENGINE_AUTHORS}");
+                println!(
+                    "option name Hash type spin default {DEFAULT_HASH_MB} min 1 max {MAX_HASH_MB}"
+                );
+                println!("uciok");
+            }
+            Some("isready") => println!("readyok"),
+            Some("ucinewgame") => {
+                if let Some(s) = search.take() {
+                    s.finish();
+                }
+                game = GameState::new();
+                table.lock().expect("Search thread panicked").clear();
+            }
+            Some("position") => match parse_position(tokens) {
+                Ok(gs) => game = gs,
+                Err(e) => println!("info string {e}"),
+            },
+            Some("go") => {
+                if let Some(s) = search.take() {
+                    s.finish();
+                }
+                search = Some(Search::start(
+                    game.clone(),
+                    parse_go(tokens),
+                    Arc::clone(&table),
+                    Arc::clone(&pawns),
+                ));
+            }
+            Some("stop") => {
+                if let Some(s) = search.take() {
+                    s.finish();
+                }
+            }
+            Some("setoption") => {
+                if let Some(s) = search.take() {
+                    s.finish();
+                }
+                match parse_option(tokens) {
+                    Some((name, value)) if name == "Hash" => match value.parse::<usize>() {
+                        Ok(mb) => table
+                            .lock()
+                            .expect("Search thread panicked")
+                            .resize(mb.clamp(1, MAX_HASH_MB)),
+                        Err(_) => println!("info string Invalid Hash size {value}"),
+                    },
+                    Some((name, _)) => println!("info string Unknown option {name}"),
+                    None => println!("info string Expected `name <id> value <x>`"),
+                }
+            }
+            Some("quit") => break,
+            _ => (), // unknown commands are ignored
+        }
+    }
+
+    if let Some(s) = search.take() {
+        s.finish();
+    }
+}
+
+/// `startpos [moves ...]` or `fen <FEN> [moves ...]`
+fn parse_position<'a, I>(mut tokens: I) -> Result<GameState, String>
+where
+    I: Iterator<Item = &'a str>,
+{
+    let mut game = match tokens.next() {
+        Some("startpos") => {
+            // skip the optional `moves` keyword
+            tokens.next();
+            GameState::new()
+        }
+        Some("fen") => {
+            let fen: Vec<&str> = tokens.by_ref().take_while(|t| *t != "moves").collect();
+            GameState::from_string(&fen.join(" ")).map_err(|e| e.to_string())?
+        }
+        _ => return Err("Expected `startpos` or `fen`".to_string()),
+    };
+
+    for move_string in tokens {
+        let (from, to, promotion) =
+            position::string_to_move(move_string).map_err(|e| e.to_string())?;
+        game.move_piece_legally(from, to, promotion)
+            .map_err(|e| e.to_string())?;
+    }
+
+    Ok(game)
+}
+
+/// `name <id> [value <x>]`, names and values may contain spaces
+fn parse_option<'a, I>(mut tokens: I) -> Option<(String, String)>
+where
+    I: Iterator<Item = &'a str>,
+{
+    if tokens.next()? != "name" {
+        return None;
+    }
+    let name: Vec<&str> = tokens.by_ref().take_while(|t| *t != "value").collect();
+    let value: Vec<&str> = tokens.collect();
+    Some((name.join(" "), value.join(" ")))
+}
+
+/// Every keyword `go` can be followed by, used to find where `searchmoves` ends
+const GO_KEYWORDS: [&str; 12] = [
+    "searchmoves",
+    "ponder",
+    "wtime",
+    "btime",
+    "winc",
+    "binc",
+    "movestogo",
+    "depth",
+    "nodes",
+    "mate",
+    "movetime",
+    "infinite",
+];
+
+/// `go [wtime N] [btime N] [winc N] [binc N] [movestogo N] [movetime N] [depth N] [nodes N] [infinite]`
+/// Times are in milliseconds, `ponder`, `mate N` and `searchmoves <moves>` are skipped
+fn parse_go<'a, I>(tokens: I) -> SearchLimits
+where
+    I: Iterator<Item = &'a str>,
+{
+    let mut limits = SearchLimits::default();
+    let mut limited = false;
+    let mut tokens = tokens.peekable();
+
+    while let Some(token) = tokens.next() {
+        match token {
+            "infinite" => {
+                limits.infinite = true;
+                limited = true;
+                continue;
+            }
+            "searchmoves" => {
+                while tokens.next_if(|t| !GO_KEYWORDS.contains(t)).is_some() {}
+                continue;
+            }
+            _ => {}
+        }
+
+        // Only the keys below take the number, anything else leaves it to be skipped
+        let value = match tokens.peek().and_then(|v| v.parse::<u64>().ok()) {
+            Some(v) => v,
+            None => continue,
+        };
+        let millis = Duration::from_millis(value);
+        match token {
+            "wtime" => limits.white_time = Some(millis),
+            "btime" => limits.black_time = Some(millis),
+            "winc" => limits.white_increment = millis,
+            "binc" => limits.black_increment = millis,
+            "movestogo" => limits.moves_to_go = Some(value as u32),
+            "movetime" => limits.move_time = Some(millis),
+            "depth" => limits.depth = Some(value as u32),
+            "nodes" => limits.nodes = Some(value),
+            _ => continue,
+        }
+        tokens.next();
+        limited = true;
+    }
+
+    // A bare `go` shouldn't think forever
+    if !limited {
+        limits.depth = Some(DEFAULT_DEPTH);
+    }
+    limits
+}
+
+/// `info` line describing a finished search
+fn info(result: &SearchResult) -> String {
+    // Mates are reported in moves rather than plies
+    let score = if result.score.abs() > MATE - 1000 {
+        let plies = MATE - result.score.abs();
+        format!("mate {}", result.score.signum() * (plies + 1) / 2)
+    } else {
+        format!("cp {}", result.score)
+    };
+
+    let pv: Vec<String> = result.pv.iter().map(|mv| mv.to_string()).collect();
+    format!(
+        "info depth {} score {score} nodes {} time {} pv {}",
+        result.depth,
+        result.nodes,
+        result.time.as_millis(),
+        pv.join(" ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position(command: &str) -> Result<String, String> {
+        parse_position(command.split_whitespace()).map(|gs| gs.to_string())
+    }
+
+    #[test]
+    fn test_parse_position() {
+        assert_eq!(position("startpos"), Ok(GameState::new().to_string()));
+        assert_eq!(
+            position("startpos moves e2e4 e7e5"),
+            Ok("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2".to_string())
+        );
+
+        let fen = "4k3/P7/8/8/8/8/8/4K3 w - - 0 1";
+        assert_eq!(position(&format!("fen {fen}")), Ok(fen.to_string()));
+        assert_eq!(
+            position(&format!("fen {fen} moves a7a8q e8d7")),
+            Ok("Q7/3k4/8/8/8/8/8/4K3 w - - 1 2".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_position_errors() {
+        assert!(position("").is_err());
+        assert!(position("fen not/a/fen w - - 0 1").is_err());
+        assert!(position("startpos moves e2e5").is_err(), "Illegal move");
+        assert!(position("startpos moves e2").is_err(), "Badly formed move");
+        assert!(
+            position("startpos moves e2e4 e2e4").is_err(),
+            "No piece to move"
+        );
+    }
+
+    #[test]
+    fn test_parse_go() {
+        let go = |command: &str| parse_go(command.split_whitespace());
+        let ms = Duration::from_millis;
+
+        assert_eq!(
+            go("wtime 60000 btime 50000 winc 1000 binc 2000 movestogo 20"),
+            SearchLimits {
+                white_time: Some(ms(60000)),
+                black_time: Some(ms(50000)),
+                white_increment: ms(1000),
+                black_increment: ms(2000),
+                moves_to_go: Some(20),
+                ..SearchLimits::default()
+            }
+        );
+        assert_eq!(
+            go("movetime 500 depth 6 nodes 10000"),
+            SearchLimits {
+                move_time: Some(ms(500)),
+                depth: Some(6),
+                nodes: Some(10000),
+                ..SearchLimits::default()
+            }
+        );
+        assert_eq!(
+            go("infinite"),
+            SearchLimits {
+                infinite: true,
+                ..SearchLimits::default()
+            }
+        );
+        assert_eq!(go(""), SearchLimits::depth(DEFAULT_DEPTH));
+        assert_eq!(
+            go("depth x ponder"),
+            SearchLimits::depth(DEFAULT_DEPTH),
+            "Nothing usable"
+        );
+        assert_eq!(
+            go("ponder wtime 1000 btime 2000"),
+            SearchLimits {
+                white_time: Some(ms(1000)),
+                black_time: Some(ms(2000)),
+                ..SearchLimits::default()
+            }
+        );
+        assert_eq!(
+            go("searchmoves e2e4 d2d4 mate 3 depth 4"),
+            SearchLimits::depth(4)
+        );
+    }
+
+    #[test]
+    fn test_parse_option() {
+        let option = |command: &str| parse_option(command.split_whitespace());
+        assert_eq!(
+            option("name Hash value 128"),
+            Some(("Hash".to_string(), "128".to_string()))
+        );
+        assert_eq!(
+            option("name Clear Hash"),
+            Some(("Clear Hash".to_string(), String::new()))
+        );
+        assert_eq!(option("Hash value 128"), None);
+    }
+
+    #[test]
+    fn test_infinite_waits_for_stop() {
+        // Checkmated, so the search itself has nothing to do
+        let game = GameState::from_string("R5k1/5ppp/8/8/8/8/8/6K1 b - - 1 1").unwrap();
+        let limits = SearchLimits {
+            infinite: true,
+            ..SearchLimits::default()
+        };
+        let search = Search::start(game, limits, Arc::default(), Arc::default());
+        thread::sleep(Duration::from_millis(50));
+        assert!(!search.handle.is_finished(), "Answered before `stop`");
+        search.finish();
+    }
+
+    #[test]
+    fn test_info() {
+        let mut result = SearchResult {
+            best_move: None,
+            score: 35,
+            pv: Vec::new(),
+            depth: 4,
+            nodes: 1234,
+            time: Duration::from_millis(56),
+        };
+        assert_eq!(
+            info(&result),
+            "info depth 4 score cp 35 nodes 1234 time 56 pv "
+        );
+
+        let mv = GameState::new().legal_moves()[0];
+        result.pv = vec![mv];
+        result.score = MATE - 3;
+        assert!(info(&result).contains("score mate 2"), "{}", info(&result));
+        assert!(info(&result).ends_with(&format!("pv {mv}")));
+        result.score = -(MATE - 2);
+        assert!(info(&result).contains("score mate -1"), "{}", info(&result));
+    }
+}
@@ -0,0 +1,326 @@
+use crate::{DEFAULT_DEPTH, ENGINE_NAME};
+use kno3_chess_engine::{
+    position, Color, Error as EngineError, GameState, PawnTable, SearchLimits, TranspositionTable,
+    MAX_HASH_MB,
+};
+use std::io::{self, BufRead};
+use std::sync::atomic::AtomicBool;
+use std::time::Duration;
+
+// https://www.gnu.org/software/xboard/engine-intf.html
+
+struct XBoard {
+    game: GameState,
+    force: bool,                   // only record moves, never reply with one
+    engine_color: Color,           // the side the engine plays
+    depth: Option<u32>,            // plies to search, set with `sd`
+    moves_per_session: u32,        // moves per time control, 0 for the whole game
+    increment: Duration,           // added after every move
+    move_time: Option<Duration>,   // fixed time per move, set with `st`
+    engine_time: Option<Duration>, // left on the engine's clock, set with `time`
+    table: TranspositionTable,     // sized with `memory`
+    pawns: PawnTable,              // pawn structure scores, kept between games
+}
+
+/// Read XBoard commands from stdin until `quit`
+pub fn run() {
+    let mut xb = XBoard::new();
+    for line in io::stdin().lock().lines() {
+        match line {
+            Ok(line) if xb.command(&line) => (),
+            _ => break,
+        }
+    }
+}
+
+impl XBoard {
+    fn new() -> Self {
+        Self {
+            game: GameState::new(),
+            force: false,
+            engine_color: Color::Black,
+            depth: None,
+            moves_per_session: 0,
+            increment: Duration::ZERO,
+            move_time: None,
+            engine_time: None,
+            table: TranspositionTable::default(),
+            pawns: PawnTable::default(),
+        }
+    }
+
+    /// Act on one line from the GUI, false once it is time to quit
+    fn command(&mut self, line: &str) -> bool {
+        let mut tokens = line.split_whitespace();
+
+        match tokens.next() {
+            Some("xboard") => (),
+            Some("protover") => {
+                println!(
+                    "feature myname=\"{ENGINE_NAME}\" usermove=1 setboard=1 ping=1 memory=1 colors=0 sigint=0 sigterm=0 done=1"
+                );
+            }
+            Some("new") => {
+                self.game = GameState::new();
+                self.force = false;
+                self.engine_color = Color::Black;
+                self.table.clear();
+            }
+            Some("force") => self.force = true,
+            Some("go") => {
+                self.force = false;
+                self.engine_color = self.game.side_to_move();
+                self.engine_move();
+            }
+            Some("usermove") => {
+                if let Some(move_string) = tokens.next() {
+                    self.user_move(move_string)
+                        .unwrap_or_else(|_| println!("Illegal move: {move_string}"));
+                }
+            }
+            Some("undo") => {
+                self.game.unmake_move();
+            }
+            Some("remove") => {
+                self.game.unmake_move();
+                self.game.unmake_move();
+            }
+            Some("setboard") => {
+                let fen: Vec<&str> = tokens.collect();
+                if let Err(e) = self.set_board(&fen.join(" ")) {
+                    println!("tellusererror Illegal position: {e}");
+                }
+            }
+            Some("memory") => {
+                if let Some(mb) = tokens.next().and_then(|m| m.parse::<usize>().ok()) {
+                    self.table.resize(mb.clamp(1, MAX_HASH_MB));
+                }
+            }
+            Some("sd") => self.depth = tokens.next().and_then(|d| d.parse().ok()),
+            Some("st") => {
+                self.move_time = tokens
+                    .next()
+                    .and_then(|t| t.parse().ok())
+                    .map(Duration::from_secs_f64);
+            }
+            Some("level") => {
+                self.moves_per_session = tokens.next().and_then(|m| m.parse().ok()).unwrap_or(0);
+                tokens.next(); // base time, the clock is sent with `time` before each move
+                self.increment = tokens
+                    .next()
+                    .and_then(|i| i.parse().ok())
+                    .map(Duration::from_secs_f64)
+                    .unwrap_or(Duration::ZERO);
+                self.move_time = None;
+            }
+            Some("time") => {
+                // centiseconds
+                self.engine_time = tokens
+                    .next()
+                    .and_then(|t| t.parse().ok())
+                    .map(|t: u64| Duration::from_millis(t * 10));
+            }
+            Some("ping") => println!("pong {}", tokens.next().unwrap_or("")),
+            Some("result") => self.force = true,
+            Some("otim") => (), // only the engine's own clock matters
+            Some("quit") => return false,
+            // Moves may be sent bare by GUIs that ignore the usermove feature
+            Some(token) if position::string_to_move(token).is_ok() => {
+                self.user_move(token)
+                    .unwrap_or_else(|_| println!("Illegal move: {token}"));
+            }
+            _ => (), // unknown commands are ignored
+        }
+        true
+    }
+
+    /// Play the opponent's move and reply to it unless in force mode
+    fn user_move(&mut self, move_string: &str) -> Result<(), EngineError> {
+        let (from, to, promotion) = position::string_to_move(move_string)?;
+        self.game.move_piece_legally(from, to, promotion)?;
+
+        if !self.report_result() && !self.force && self.game.side_to_move() == self.engine_color {
+            self.engine_move();
+        }
+        Ok(())
+    }
+
+    /// Start again from `fen`, keeping the current game if it can't be read
+    fn set_board(&mut self, fen: &str) -> Result<(), EngineError> {
+        self.game = GameState::from_string(fen)?;
+        Ok(())
+    }
+
+    fn engine_move(&mut self) {
+        let limits = self.limits();
+        let result = self.game.search_with_limits(
+            &limits,
+            &mut self.table,
+            &mut self.pawns,
+            &AtomicBool::new(false),
+            |_| (),
+        );
+        if let Some(mv) = result.best_move {
+            self.game.make_move(mv);
+            println!("move {mv}");
+        }
+        self.report_result();
+    }
+
+    /// Turn the clock settings into limits for the side the engine is playing
+    fn limits(&self) -> SearchLimits {
+        let mut limits = SearchLimits {
+            depth: self.depth,
+            move_time: self.move_time,
+            ..SearchLimits::default()
+        };
+
+        if let Some(time) = self.engine_time.filter(|_| self.move_time.is_none()) {
+            if self.game.side_to_move().is_white() {
+                limits.white_time = Some(time);
+                limits.white_increment = self.increment;
+            } else {
+                limits.black_time = Some(time);
+                limits.black_increment = self.increment;
+            }
+            if self.moves_per_session > 0 {
+                let played = (self.game.move_count() - 1) % self.moves_per_session;
+                limits.moves_to_go = Some(self.moves_per_session - played);
+            }
+        }
+
+        // Without a clock fall back to a fixed depth
+        if limits.move_time.is_none() && limits.white_time.is_none() && limits.black_time.is_none()
+        {
+            limits.depth = limits.depth.or(Some(DEFAULT_DEPTH));
+        }
+        limits
+    }
+
+    /// Tell the GUI if the game is over
+    fn report_result(&self) -> bool {
+        match self.game.result() {
+            Some(result) => {
+                println!("{result}");
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An XBoard that has been sent `commands`, in force mode so it never searches
+    fn xboard(commands: &[&str]) -> XBoard {
+        let mut xb = XBoard::new();
+        xb.command("force");
+        for command in commands {
+            assert!(xb.command(command), "{command} quit");
+        }
+        xb
+    }
+
+    #[test]
+    fn test_no_clock() {
+        assert_eq!(xboard(&[]).limits(), SearchLimits::depth(DEFAULT_DEPTH));
+        assert_eq!(xboard(&["sd 4"]).limits(), SearchLimits::depth(4));
+        assert_eq!(
+            xboard(&["st 2"]).limits(),
+            SearchLimits {
+                move_time: Some(Duration::from_secs(2)),
+                ..SearchLimits::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_level() {
+        let clock = SearchLimits {
+            white_time: Some(Duration::from_secs(60)),
+            white_increment: Duration::from_secs(2),
+            moves_to_go: Some(40),
+            ..SearchLimits::default()
+        };
+        let mut xb = xboard(&["level 40 5 2", "time 6000", "otim 1234"]);
+        assert_eq!(xb.limits(), clock);
+
+        // Black's clock after white's move, then white's with one move fewer to go
+        xb.command("usermove e2e4");
+        assert_eq!(
+            xb.limits(),
+            SearchLimits {
+                black_time: Some(Duration::from_secs(60)),
+                black_increment: Duration::from_secs(2),
+                moves_to_go: Some(40),
+                ..SearchLimits::default()
+            }
+        );
+        xb.command("usermove e7e5");
+        assert_eq!(xb.limits().moves_to_go, Some(39));
+
+        // Incremental and sudden death controls have no moves to go
+        let xb = xboard(&["level 0 2:30 0.5", "time 6000"]);
+        assert_eq!(
+            xb.limits(),
+            SearchLimits {
+                white_time: Some(Duration::from_secs(60)),
+                white_increment: Duration::from_millis(500),
+                ..SearchLimits::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_move_time_beats_clock() {
+        let move_time = SearchLimits {
+            move_time: Some(Duration::from_secs(3)),
+            ..SearchLimits::default()
+        };
+        assert_eq!(xboard(&["time 6000", "st 3"]).limits(), move_time);
+        // A new time control replaces a fixed time per move
+        assert_eq!(
+            xboard(&["st 3", "level 40 5 0", "time 6000"]).limits(),
+            SearchLimits {
+                white_time: Some(Duration::from_secs(60)),
+                moves_to_go: Some(40),
+                ..SearchLimits::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_user_move() {
+        let mut xb = xboard(&["usermove e2e4", "e7e5"]);
+        let fen = "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2";
+        assert_eq!(xb.game.to_string(), fen);
+
+        assert!(matches!(
+            xb.user_move("e4e5"),
+            Err(EngineError::IllegalMove { .. })
+        ));
+        assert!(matches!(
+            xb.user_move("e4"),
+            Err(EngineError::InvalidMoveFormat(_))
+        ));
+        assert_eq!(xb.game.to_string(), fen, "Illegal moves changed the game");
+    }
+
+    #[test]
+    fn test_set_board() {
+        let fen = "4k3/8/8/8/8/8/8/4K2R w K - 0 1";
+        let mut xb = xboard(&[&format!("setboard {fen}")]);
+        assert_eq!(xb.game.to_string(), fen);
+
+        assert!(xb.set_board("4k3/8/8/8/8/8/8/4K2X w K - 0 1").is_err());
+        xb.command("setboard nonsense");
+        assert_eq!(xb.game.to_string(), fen, "Bad positions replaced the game");
+    }
+
+    #[test]
+    fn test_quit() {
+        assert!(!xboard(&[]).command("quit"));
+    }
+}
@@ -0,0 +1,507 @@
+use std::sync::OnceLock;
+
+use crate::{Bitboard, Color, Square};
+
+// https://www.chessprogramming.org/Magic_Bitboards
+// Every attack set is looked up rather than worked out square by square
+
+const KNIGHT_JUMPS: [(i8, i8); 8] = [
+    (-1, -2),
+    (-1, 2),
+    (1, -2),
+    (1, 2),
+    (-2, -1),
+    (-2, 1),
+    (2, -1),
+    (2, 1),
+];
+/// (rank, file) steps of the rays rooks and bishops slide along
+const ROOK_DIRECTIONS: [(i8, i8); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+const BISHOP_DIRECTIONS: [(i8, i8); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+
+const KNIGHT_ATTACKS: [u64; 64] = jump_table(&KNIGHT_JUMPS);
+const KING_ATTACKS: [u64; 64] = king_table();
+/// Squares a pawn attacks, white's first
+const PAWN_ATTACKS: [[u64; 64]; 2] = [pawn_table(Color::White), pawn_table(Color::Black)];
+
+/// The square `(d_rank, d_file)` away, None if that is off the board
+const fn offset(square: u8, (d_rank, d_file): (i8, i8)) -> Option<u8> {
+    let rank = (square / 8) as i8 + d_rank;
+    let file = (square % 8) as i8 + d_file;
+    if rank < 0 || rank > 7 || file < 0 || file > 7 {
+        None
+    } else {
+        Some((rank * 8 + file) as u8)
+    }
+}
+
+/// Squares reached by a single step of each offset from every square
+const fn jump_table(steps: &[(i8, i8)]) -> [u64; 64] {
+    let mut table = [0; 64];
+    let mut square = 0;
+    while square < 64 {
+        let mut i = 0;
+        while i < steps.len() {
+            if let Some(target) = offset(square, steps[i]) {
+                table[square as usize] |= 1 << target;
+            }
+            i += 1;
+        }
+        square += 1;
+    }
+    table
+}
+
+/// Masking off the edge file before shifting sideways stops a step from wrapping
+/// around onto the other side of the board
+const fn king_table() -> [u64; 64] {
+    let mut table = [0; 64];
+    let mut square = 0;
+    while square < 64 {
+        let king = Bitboard(1 << square);
+        let row = king.0 | king.west().0 | king.east().0;
+        table[square] = (row | row << 8 | row >> 8) & !king.0;
+        square += 1;
+    }
+    table
+}
+
+const fn pawn_table(color: Color) -> [u64; 64] {
+    let mut table = [0; 64];
+    let mut square = 0;
+    while square < 64 {
+        table[square] = all_pawn_attacks(Bitboard(1 << square), color).0;
+        square += 1;
+    }
+    table
+}
+
+pub(crate) fn knight_attacks(square: Square) -> Bitboard {
+    Bitboard(KNIGHT_ATTACKS[square.index() as usize])
+}
+
+pub(crate) fn king_attacks(square: Square) -> Bitboard {
+    Bitboard(KING_ATTACKS[square.index() as usize])
+}
+
+/// Squares a `color` pawn on `square` could capture on
+pub(crate) fn pawn_attacks(square: Square, color: Color) -> Bitboard {
+    Bitboard(PAWN_ATTACKS[color as usize][square.index() as usize])
+}
+
+/// Squares attacked by any of these pawns, worked out for the whole set at once
+pub(crate) const fn all_pawn_attacks(pawns: Bitboard, color: Color) -> Bitboard {
+    let ahead = match color {
+        Color::White => pawns.north(),
+        Color::Black => pawns.south(),
+    };
+    Bitboard(ahead.west().0 | ahead.east().0)
+}
+
+/// Walk each ray until it leaves the board or hits a piece, which is included
+/// Slow, only used to fill the tables
+fn ray_attacks(square: u8, occupied: u64, directions: &[(i8, i8); 4]) -> u64 {
+    let mut result = 0;
+    for &direction in directions {
+        let mut current = square;
+        while let Some(next) = offset(current, direction) {
+            result |= 1 << next;
+            if occupied & (1 << next) != 0 {
+                break;
+            }
+            current = next;
+        }
+    }
+    result
+}
+
+/// Squares on the rays whose occupancy changes the attacks
+/// The last square of each ray never blocks anything so it is left out
+fn relevant_occupancy(square: u8, directions: &[(i8, i8); 4]) -> u64 {
+    let mut result = 0;
+    for &direction in directions {
+        let mut current = square;
+        while let Some(next) = offset(current, direction) {
+            if offset(next, direction).is_none() {
+                break;
+            }
+            result |= 1 << next;
+            current = next;
+        }
+    }
+    result
+}
+
+/// Found by trying random sparse numbers until every arrangement of blockers
+/// landed in a slot without clashing with a different attack set
+const ROOK_MAGICS: [u64; 64] = [
+    0x4080_0040_041A_2080,
+    0x04C0_0020_0910_0040,
+    0x1080_2000_0810_0082,
+    0x0100_0810_0020_0502,
+    0x0100_1008_0005_0002,
+    0x0100_0400_0100_0802,
+    0x0400_1401_0230_9008,
+    0x4280_0821_0008_4880,
+    0x0000_8000_8020_4004,
+    0x2884_8080_2000_4000,
+    0x0040_8020_0080_1000,
+    0x0404_8010_0008_0082,
+    0x4005_0010_8801_0004,
+    0x1001_0002_0400_0900,
+    0x0101_0002_0004_0100,
+    0x4024_8020_4100_0080,
+    0x0002_0200_2040_8100,
+    0x80E0_0080_4000_2080,
+    0x0001_0300_4020_0010,
+    0x0000_8080_1000_0800,
+    0x0140_8080_0800_0400,
+    0x0011_0100_0400_0802,
+    0x01C8_0400_4201_3088,
+    0x0200_8200_0060_9401,
+    0x8040_0040_8000_8020,
+    0x1000_2000_4000_5000,
+    0x0010_0400_2008_0020,
+    0x0040_1000_8080_0800,
+    0x0080_0800_8080_0400,
+    0x2002_0004_0400_1020,
+    0x6000_8104_0002_0810,
+    0x0068_0082_0001_0044,
+    0x0080_0040_0040_2000,
+    0x0C00_4000_8280_200C,
+    0x0400_8020_0080_1000,
+    0x0041_0020_0900_1005,
+    0x9402_0800_8280_0400,
+    0x9082_0004_0080_8002,
+    0x4180_0102_0400_0810,
+    0x1000_4920_D200_0684,
+    0x0001_2080_4011_8000,
+    0x0000_8200_2102_0042,
+    0x0260_4020_8202_0010,
+    0x9500_1001_0009_0020,
+    0x1808_0004_0082_8008,
+    0x102A_0008_1002_0004,
+    0x0000_0402_0001_0100,
+    0x8082_0061_0082_0004,
+    0x0080_1040_0020_0940,
+    0x0020_2100_8040_0900,
+    0x0601_0014_4420_0100,
+    0x0010_0100_2408_1100,
+    0x8003_0010_8488_0100,
+    0x1004_8004_0002_0080,
+    0x4808_8108_8210_0400,
+    0x0000_2401_0444_8A00,
+    0x0112_6011_0480_0145,
+    0x040D_0342_0010_2086,
+    0x3501_0920_0102_1241,
+    0x2288_2004_0900_1001,
+    0x2882_0010_0420_0802,
+    0x0A02_0004_2890_1B22,
+    0x0A00_0122_1010_9804,
+    0x0080_0040_2104_0082,
+];
+const BISHOP_MAGICS: [u64; 64] = [
+    0x0004_1002_0200_2600,
+    0x0042_0202_020E_0800,
+    0x0150_00C2_0148_2000,
+    0x0084_0400_8828_80A0,
+    0x0094_0308_0400_2000,
+    0x0090_8220_20E8_4400,
+    0x1508_844C_0240_8020,
+    0x410A_0200_4208_0480,
+    0x0802_1010_8240_9410,
+    0x0000_1044_0800_7440,
+    0x0002_4808_2108_2010,
+    0x0200_2405_1201_0000,
+    0x0092_0110_4090_0209,
+    0x1002_0205_1C20_2010,
+    0x0000_0101_2860_0400,
+    0x0024_0201_841C_0300,
+    0x0040_0110_5002_208C,
+    0xA008_0002_8204_0402,
+    0x00C8_0410_1088_2009,
+    0x0408_0C00_8200_4000,
+    0x8084_022A_80A0_2200,
+    0x8A04_8008_0814_020C,
+    0x0420_8080_4210_1002,
+    0x0104_2004_8208_0200,
+    0x0108_4020_0802_0810,
+    0x0002_9000_A204_2802,
+    0x2210_2080_5008_8082,
+    0x0D01_0801_2100_4100,
+    0x0028_8400_2080_2007,
+    0x0010_0020_0504_0101,
+    0x008C_2040_8082_1014,
+    0x8008_A220_4201_0402,
+    0x2014_9040_0068_6200,
+    0x2108_0104_2210_0488,
+    0x0000_2208_0081_0801,
+    0x0000_8200_8018_0480,
+    0x0000_4200_2202_0080,
+    0xA002_0042_0563_0082,
+    0x0401_420A_1040_9800,
+    0x4222_4403_0090_4054,
+    0x0022_1004_2610_6080,
+    0x0004_0104_1084_4248,
+    0xA041_0406_0202_2100,
+    0xA200_3040_2200_9021,
+    0x0008_A801_04C0_8400,
+    0x0720_2881_0200_0042,
+    0x0018_8A20_8400_0201,
+    0x0101_1C03_0F42_0208,
+    0xE008_8228_2084_0180,
+    0x0006_00A0_8808_2220,
+    0x8020_0086_0341_0000,
+    0x0300_0000_4202_0000,
+    0x0008_3010_0202_0C10,
+    0x0000_4028_820C_200F,
+    0x6050_2062_18CA_0020,
+    0x2002_8202_0206_0040,
+    0x0021_00C2_0231_4028,
+    0x0830_0104_8801_0860,
+    0x160C_0800_2422_0800,
+    0x0000_2004_4042_0206,
+    0x0000_0050_0490_4402,
+    0x0000_0220_0C10_0289,
+    0x2440_4070_4902_008C,
+    0x3021_0A08_8801_0840,
+];
+
+/// Multiplying the relevant pieces by `magic` maps every arrangement of blockers
+/// to a slot holding its attacks
+struct Magic {
+    mask: u64,
+    magic: u64,
+    shift: u32,
+    offset: usize, // where this square's slots start in `SliderTables::attacks`
+}
+
+impl Magic {
+    fn index(&self, occupied: u64) -> usize {
+        self.offset + ((occupied & self.mask).wrapping_mul(self.magic) >> self.shift) as usize
+    }
+}
+
+struct SliderTables {
+    rook: Vec<Magic>,
+    bishop: Vec<Magic>,
+    attacks: Vec<u64>, // shared by every square of both pieces
+}
+
+/// Built the first time a slider's attacks are needed
+static SLIDERS: OnceLock<SliderTables> = OnceLock::new();
+
+fn sliders() -> &'static SliderTables {
+    SLIDERS.get_or_init(|| {
+        let mut attacks = Vec::new();
+        let rook = (0..64)
+            .map(|sq| fill_slots(sq, &ROOK_DIRECTIONS, ROOK_MAGICS[sq as usize], &mut attacks))
+            .collect();
+        let bishop = (0..64)
+            .map(|sq| {
+                fill_slots(
+                    sq,
+                    &BISHOP_DIRECTIONS,
+                    BISHOP_MAGICS[sq as usize],
+                    &mut attacks,
+                )
+            })
+            .collect();
+        SliderTables {
+            rook,
+            bishop,
+            attacks,
+        }
+    })
+}
+
+/// Add the attacks for every arrangement of blockers around `square` to `attacks`
+fn fill_slots(square: u8, directions: &[(i8, i8); 4], magic: u64, attacks: &mut Vec<u64>) -> Magic {
+    let mask = relevant_occupancy(square, directions);
+    let entry = Magic {
+        mask,
+        magic,
+        shift: 64 - mask.count_ones(),
+        offset: attacks.len(),
+    };
+    attacks.resize(attacks.len() + (1 << mask.count_ones()), 0);
+
+    for subset in subsets(mask) {
+        // Sliders always attack something, so an empty slot is still 0
+        let ray = ray_attacks(square, subset, directions);
+        let slot = &mut attacks[entry.index(subset)];
+        assert!(
+            *slot == 0 || *slot == ray,
+            "Magic {magic:#x} for square {square} puts different attacks in one slot"
+        );
+        *slot = ray;
+    }
+    entry
+}
+
+/// Every subset of `mask`, starting with the empty one
+/// https://www.chessprogramming.org/Traversing_Subsets_of_a_Set
+fn subsets(mask: u64) -> impl Iterator<Item = u64> {
+    let mut next = Some(0_u64);
+    std::iter::from_fn(move || {
+        let subset = next?;
+        let following = subset.wrapping_sub(mask) & mask;
+        next = (following != 0).then_some(following);
+        Some(subset)
+    })
+}
+
+/// Squares a rook on `square` attacks, up to and including the first piece on each ray
+pub(crate) fn rook_attacks(square: Square, occupied: Bitboard) -> Bitboard {
+    let tables = sliders();
+    Bitboard(tables.attacks[tables.rook[square.index() as usize].index(occupied.0)])
+}
+
+/// Squares a bishop on `square` attacks, up to and including the first piece on each ray
+pub(crate) fn bishop_attacks(square: Square, occupied: Bitboard) -> Bitboard {
+    let tables = sliders();
+    Bitboard(tables.attacks[tables.bishop[square.index() as usize].index(occupied.0)])
+}
+
+pub(crate) fn queen_attacks(square: Square, occupied: Bitboard) -> Bitboard {
+    rook_attacks(square, occupied) | bishop_attacks(square, occupied)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jump_tables() {
+        assert_eq!(
+            knight_attacks(Square::A1),
+            Bitboard::from([Square::C2, Square::B3])
+        );
+        assert_eq!(knight_attacks(Square::D4).count(), 8);
+        assert_eq!(
+            king_attacks(Square::H1),
+            Bitboard::from([Square::G1, Square::G2, Square::H2])
+        );
+        assert_eq!(king_attacks(Square::E5).count(), 8);
+
+        assert_eq!(
+            pawn_attacks(Square::A2, Color::White),
+            Square::B3.bitboard(),
+            "a2 only attacks b3"
+        );
+        assert_eq!(
+            pawn_attacks(Square::H2, Color::White),
+            Square::G3.bitboard(),
+            "h2 only attacks g3"
+        );
+        assert_eq!(
+            pawn_attacks(Square::E7, Color::Black),
+            Bitboard::from([Square::D6, Square::F6])
+        );
+        assert!(
+            pawn_attacks(Square::E8, Color::White).is_empty(),
+            "Off the board"
+        );
+        assert_eq!(
+            all_pawn_attacks(Bitboard::from([Square::A2, Square::H7]), Color::Black),
+            Bitboard::from([Square::B1, Square::G6]),
+            "Whole sets at once"
+        );
+    }
+
+    #[test]
+    fn test_relevant_occupancy() {
+        // A1 rook: A2-A7 and B1-G1
+        assert_eq!(relevant_occupancy(0, &ROOK_DIRECTIONS).count_ones(), 12);
+        // D4 bishop can be blocked on 9 squares
+        assert_eq!(relevant_occupancy(27, &BISHOP_DIRECTIONS).count_ones(), 9);
+    }
+
+    #[test]
+    fn test_slider_attacks() {
+        let occupied = Bitboard::from([
+            Square::D1,
+            Square::A4,
+            Square::E4,
+            Square::D7,
+            Square::F6,
+            Square::B2,
+        ]);
+        assert_eq!(
+            rook_attacks(Square::D4, occupied),
+            Bitboard::from([
+                Square::D3,
+                Square::D2,
+                Square::D1,
+                Square::C4,
+                Square::B4,
+                Square::A4,
+                Square::E4,
+                Square::D5,
+                Square::D6,
+                Square::D7
+            ])
+        );
+        assert_eq!(
+            bishop_attacks(Square::D4, occupied),
+            Bitboard::from([
+                Square::C3,
+                Square::B2,
+                Square::E3,
+                Square::F2,
+                Square::G1,
+                Square::C5,
+                Square::B6,
+                Square::A7,
+                Square::E5,
+                Square::F6
+            ])
+        );
+        assert_eq!(
+            queen_attacks(Square::D4, occupied),
+            rook_attacks(Square::D4, occupied) | bishop_attacks(Square::D4, occupied)
+        );
+    }
+
+    #[test]
+    fn test_magics_match_rays() {
+        // Every arrangement of blockers each magic has to tell apart, with the squares
+        // outside the mask empty and then full as they must make no difference
+        for square in Square::all() {
+            for directions in [&ROOK_DIRECTIONS, &BISHOP_DIRECTIONS] {
+                let mask = relevant_occupancy(square.index(), directions);
+                for subset in subsets(mask) {
+                    for occupied in [subset, subset | !mask] {
+                        let expected = ray_attacks(square.index(), occupied, directions);
+                        let attacks = if directions == &ROOK_DIRECTIONS {
+                            rook_attacks(square, Bitboard(occupied))
+                        } else {
+                            bishop_attacks(square, Bitboard(occupied))
+                        };
+                        assert_eq!(attacks.0, expected, "{square} {occupied:#x}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_subsets() {
+        let mut all: Vec<u64> = subsets(0b1010_0001).collect();
+        all.sort();
+        assert_eq!(
+            all,
+            vec![
+                0,
+                0b1,
+                0b10_0000,
+                0b10_0001,
+                0b1000_0000,
+                0b1000_0001,
+                0b1010_0000,
+                0b1010_0001
+            ]
+        );
+        assert_eq!(subsets(0).collect::<Vec<_>>(), vec![0]);
+    }
+}
@@ -0,0 +1,201 @@
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not, Shl, Shr};
+
+use crate::Square;
+
+/// A set of squares, one bit each with A1 as the lowest bit
+/// https://www.chessprogramming.org/Bitboards
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Bitboard(pub u64);
+
+impl Bitboard {
+    pub const EMPTY: Bitboard = Bitboard(0);
+    pub const FULL: Bitboard = Bitboard(u64::MAX);
+    pub const FILE_A: Bitboard = Bitboard(0x0101_0101_0101_0101);
+    pub const FILE_H: Bitboard = Bitboard(0x8080_8080_8080_8080);
+    pub const RANK_1: Bitboard = Bitboard(0xFF);
+    pub const RANK_8: Bitboard = Bitboard(0xFF << 56);
+
+    /// Every square on this file, counted from 0 for the A file
+    pub const fn file(file: u8) -> Bitboard {
+        Bitboard(Self::FILE_A.0 << file)
+    }
+
+    /// Every square on this rank, counted from 0 for the first rank
+    pub const fn rank(rank: u8) -> Bitboard {
+        Bitboard(Self::RANK_1.0 << (rank * 8))
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub const fn contains(self, square: Square) -> bool {
+        self.0 & square.bitboard().0 != 0
+    }
+
+    pub const fn count(self) -> u32 {
+        self.0.count_ones()
+    }
+
+    /// The lowest square in the set
+    pub const fn first(self) -> Option<Square> {
+        if self.0 == 0 {
+            None
+        } else {
+            Some(Square::from_index(self.0.trailing_zeros() as u8))
+        }
+    }
+
+    /// Every square one rank up, those on the eighth rank fall off the board
+    pub const fn north(self) -> Bitboard {
+        Bitboard(self.0 << 8)
+    }
+
+    pub const fn south(self) -> Bitboard {
+        Bitboard(self.0 >> 8)
+    }
+
+    /// Every square one file right, those on the H file are dropped rather
+    /// than wrapping around to the A file
+    pub const fn east(self) -> Bitboard {
+        Bitboard((self.0 & !Self::FILE_H.0) << 1)
+    }
+
+    pub const fn west(self) -> Bitboard {
+        Bitboard((self.0 & !Self::FILE_A.0) >> 1)
+    }
+}
+
+impl From<Square> for Bitboard {
+    fn from(square: Square) -> Self {
+        square.bitboard()
+    }
+}
+
+impl<const N: usize> From<[Square; N]> for Bitboard {
+    fn from(squares: [Square; N]) -> Self {
+        squares.into_iter().collect()
+    }
+}
+
+impl FromIterator<Square> for Bitboard {
+    fn from_iter<I: IntoIterator<Item = Square>>(squares: I) -> Self {
+        squares
+            .into_iter()
+            .fold(Bitboard::EMPTY, |set, square| set | square.bitboard())
+    }
+}
+
+/// The squares of a bitboard from A1 to H8
+pub struct Squares(u64);
+
+impl Iterator for Squares {
+    type Item = Square;
+
+    fn next(&mut self) -> Option<Square> {
+        let square = Bitboard(self.0).first()?;
+        self.0 &= self.0 - 1;
+        Some(square)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let count = self.0.count_ones() as usize;
+        (count, Some(count))
+    }
+}
+
+impl IntoIterator for Bitboard {
+    type Item = Square;
+    type IntoIter = Squares;
+
+    fn into_iter(self) -> Squares {
+        Squares(self.0)
+    }
+}
+
+impl Not for Bitboard {
+    type Output = Bitboard;
+
+    fn not(self) -> Bitboard {
+        Bitboard(!self.0)
+    }
+}
+
+impl Shl<u32> for Bitboard {
+    type Output = Bitboard;
+
+    fn shl(self, shift: u32) -> Bitboard {
+        Bitboard(self.0 << shift)
+    }
+}
+
+impl Shr<u32> for Bitboard {
+    type Output = Bitboard;
+
+    fn shr(self, shift: u32) -> Bitboard {
+        Bitboard(self.0 >> shift)
+    }
+}
+
+/// `a & b`, `a |= b` and so on act on the sets of squares
+macro_rules! set_operator {
+    ($op:ident, $method:ident, $assign_op:ident, $assign_method:ident) => {
+        impl $op for Bitboard {
+            type Output = Bitboard;
+
+            fn $method(self, other: Bitboard) -> Bitboard {
+                Bitboard(self.0.$method(other.0))
+            }
+        }
+
+        impl $assign_op for Bitboard {
+            fn $assign_method(&mut self, other: Bitboard) {
+                self.0.$assign_method(other.0);
+            }
+        }
+    };
+}
+
+set_operator!(BitAnd, bitand, BitAndAssign, bitand_assign);
+set_operator!(BitOr, bitor, BitOrAssign, bitor_assign);
+set_operator!(BitXor, bitxor, BitXorAssign, bitxor_assign);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_iteration() {
+        let squares: Vec<Square> = Bitboard(0b10101010).into_iter().collect();
+        assert_eq!(
+            squares,
+            vec![Square::B1, Square::D1, Square::F1, Square::H1]
+        );
+        assert_eq!(Bitboard::EMPTY.into_iter().next(), None);
+        assert_eq!(
+            squares.into_iter().collect::<Bitboard>(),
+            Bitboard(0b10101010)
+        );
+    }
+
+    #[test]
+    fn test_shifts() {
+        let edges = Square::A4.bitboard() | Square::H4.bitboard();
+        assert_eq!(edges.east(), Square::B4.bitboard(), "H file wrapped around");
+        assert_eq!(edges.west(), Square::G4.bitboard(), "A file wrapped around");
+        assert_eq!(Bitboard::RANK_8.north(), Bitboard::EMPTY);
+        assert_eq!(Bitboard::rank(3).south(), Bitboard::rank(2));
+        assert_eq!(Bitboard::file(7), Bitboard::FILE_H);
+    }
+
+    #[test]
+    fn test_set_operations() {
+        let a = Bitboard::FILE_A;
+        assert!(a.contains(Square::A8));
+        assert!(!a.contains(Square::B8));
+        assert_eq!((a & Bitboard::RANK_1).first(), Some(Square::A1));
+        assert_eq!((a | Bitboard::FILE_H).count(), 16);
+        assert!((a ^ a).is_empty());
+        assert_eq!(!Bitboard::EMPTY, Bitboard::FULL);
+    }
+}
@@ -0,0 +1,113 @@
+use std::fmt::{self, Display};
+use std::ops::{BitOr, BitOrAssign};
+
+use crate::Color;
+
+/// Which of the four castling moves are still allowed, only ever the KQkq bits
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CastlingRights(u8);
+
+impl CastlingRights {
+    pub const NONE: Self = Self(0);
+    pub const WHITE_KINGSIDE: Self = Self(0b1000);
+    pub const WHITE_QUEENSIDE: Self = Self(0b0100);
+    pub const BLACK_KINGSIDE: Self = Self(0b0010);
+    pub const BLACK_QUEENSIDE: Self = Self(0b0001);
+    pub const ALL: Self = Self(0b1111);
+
+    pub const fn kingside(color: Color) -> Self {
+        match color {
+            Color::White => Self::WHITE_KINGSIDE,
+            Color::Black => Self::BLACK_KINGSIDE,
+        }
+    }
+
+    pub const fn queenside(color: Color) -> Self {
+        match color {
+            Color::White => Self::WHITE_QUEENSIDE,
+            Color::Black => Self::BLACK_QUEENSIDE,
+        }
+    }
+
+    /// The right a FEN letter stands for, `K`, `Q`, `k` or `q`
+    pub const fn from_char(c: char) -> Option<Self> {
+        match c {
+            'K' => Some(Self::WHITE_KINGSIDE),
+            'Q' => Some(Self::WHITE_QUEENSIDE),
+            'k' => Some(Self::BLACK_KINGSIDE),
+            'q' => Some(Self::BLACK_QUEENSIDE),
+            _ => None,
+        }
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Are all of `rights` still allowed
+    pub const fn contains(self, rights: Self) -> bool {
+        self.0 & rights.0 == rights.0
+    }
+
+    pub fn remove(&mut self, rights: Self) {
+        self.0 &= !rights.0;
+    }
+
+    /// A different number from 0 to 15 for every combination, for looking up keys
+    pub(crate) const fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl BitOr for CastlingRights {
+    type Output = Self;
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for CastlingRights {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl Display for CastlingRights {
+    /// As in a FEN string, `KQkq` or `-` when there are none
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "-");
+        }
+        for c in ['K', 'Q', 'k', 'q'] {
+            if Self::from_char(c).is_some_and(|right| self.contains(right)) {
+                write!(f, "{c}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        assert_eq!(CastlingRights::ALL.to_string(), "KQkq");
+        assert_eq!(CastlingRights::NONE.to_string(), "-");
+        assert_eq!(
+            (CastlingRights::WHITE_QUEENSIDE | CastlingRights::BLACK_KINGSIDE).to_string(),
+            "Qk"
+        );
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut rights = CastlingRights::ALL;
+        rights.remove(CastlingRights::kingside(Color::White) | CastlingRights::BLACK_QUEENSIDE);
+        assert_eq!(rights.to_string(), "Qk");
+        assert!(rights.contains(CastlingRights::queenside(Color::White)));
+        assert!(!rights.contains(CastlingRights::WHITE_KINGSIDE));
+        assert!(!rights.contains(CastlingRights::ALL));
+    }
+}
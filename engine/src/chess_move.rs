@@ -0,0 +1,132 @@
+use std::fmt::{self, Display};
+
+use crate::{GameState, Piece, PieceKind, Square};
+
+/// A single move along with everything needed to play (and describe) it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Move {
+    pub from: Square,
+    pub to: Square,
+    pub piece: Piece,                 // piece being moved
+    pub captured: Option<Piece>,      // piece being taken, including en passant pawns
+    pub promotion: Option<PieceKind>, // what a pawn becomes on the last rank
+    pub flags: u8,                    // CASTLE | EN_PASSANT | DOUBLE_PUSH
+}
+
+impl Move {
+    pub const CASTLE: u8 = 0b001;
+    pub const EN_PASSANT: u8 = 0b010;
+    pub const DOUBLE_PUSH: u8 = 0b100;
+
+    /// Describe moving the piece on `from` to `to` in this position
+    /// None if there is no piece to move
+    pub fn new(
+        state: &GameState,
+        from: Square,
+        to: Square,
+        promotion: Option<PieceKind>,
+    ) -> Option<Self> {
+        let piece = state.board.piece_at_position(from)?;
+        let is_pawn = piece.kind == PieceKind::Pawn;
+        let mut captured = state.board.piece_at_position(to);
+        let mut flags = 0;
+
+        if piece.kind == PieceKind::King && from.index().abs_diff(to.index()) == 2 {
+            flags |= Move::CASTLE;
+        }
+        if is_pawn && from.index().abs_diff(to.index()) == 16 {
+            flags |= Move::DOUBLE_PUSH;
+        }
+        if is_pawn && Some(to) == state.en_passant && from.file() != to.file() && captured.is_none()
+        {
+            flags |= Move::EN_PASSANT;
+            captured = Some(Piece::new(!piece.color, PieceKind::Pawn));
+        }
+
+        Some(Self {
+            from,
+            to,
+            piece,
+            captured,
+            promotion,
+            flags,
+        })
+    }
+
+    pub fn is_capture(&self) -> bool {
+        self.captured.is_some()
+    }
+
+    pub fn is_castle(&self) -> bool {
+        self.flags & Move::CASTLE != 0
+    }
+
+    pub fn is_en_passant(&self) -> bool {
+        self.flags & Move::EN_PASSANT != 0
+    }
+
+    pub fn is_double_push(&self) -> bool {
+        self.flags & Move::DOUBLE_PUSH != 0
+    }
+
+    pub fn is_white(&self) -> bool {
+        self.piece.is_white()
+    }
+}
+
+impl Display for Move {
+    /// Long algebraic notation `e2e4`, `e7e8q`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.from, self.to)?;
+        if let Some(p) = self.promotion {
+            write!(f, "{p}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_move() {
+        let gs = GameState::new();
+        let mv = Move::new(&gs, Square::E2, Square::E4, None).unwrap();
+        assert_eq!(mv.piece, Piece::from_char('P').unwrap());
+        assert_eq!(mv.captured, None);
+        assert!(mv.is_double_push());
+        assert!(!mv.is_castle() && !mv.is_en_passant());
+        assert_eq!(mv.to_string(), "e2e4");
+
+        assert!(
+            Move::new(&gs, Square::E4, Square::E5, None).is_none(),
+            "Moved empty square"
+        );
+    }
+
+    #[test]
+    fn test_special_moves() {
+        let gs = GameState::from_string("r3k2r/8/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1").unwrap();
+
+        let castle = Move::new(&gs, Square::E1, Square::G1, None).unwrap();
+        assert!(castle.is_castle());
+        assert!(!castle.is_capture());
+
+        let en_passant = Move::new(&gs, Square::E5, Square::D6, None).unwrap();
+        assert!(en_passant.is_en_passant());
+        assert_eq!(en_passant.captured, Piece::from_char('p'));
+
+        let capture = Move::new(&gs, Square::A1, Square::A8, None).unwrap();
+        assert_eq!(capture.captured, Piece::from_char('r'));
+        assert_eq!(capture.flags, 0);
+    }
+
+    #[test]
+    fn test_display_promotion() {
+        let gs = GameState::from_string("7k/P7/8/8/8/8/8/K7 w - - 0 1").unwrap();
+        let mv = Move::new(&gs, Square::A7, Square::A8, Some(PieceKind::Queen)).unwrap();
+        assert_eq!(mv.promotion, Some(PieceKind::Queen));
+        assert_eq!(mv.to_string(), "a7a8q");
+    }
+}
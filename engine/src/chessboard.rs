@@ -1,37 +1,38 @@
-use crate::position::rank_file_to_square;
+use crate::{Bitboard, Color, Error, FenField, FenMode, Piece, PieceKind, Square};
 use std::cmp::PartialEq;
 use std::fmt::{self, Display};
 
+#[derive(Clone)]
 pub struct Chessboard {
-    pub black_pawns: u64,
-    pub black_rooks: u64,
-    pub black_knights: u64,
-    pub black_bishops: u64,
-    pub black_queen: u64,
-    pub black_king: u64,
-    pub white_pawns: u64,
-    pub white_rooks: u64,
-    pub white_knights: u64,
-    pub white_bishops: u64,
-    pub white_queen: u64,
-    pub white_king: u64,
+    pub black_pawns: Bitboard,
+    pub black_rooks: Bitboard,
+    pub black_knights: Bitboard,
+    pub black_bishops: Bitboard,
+    pub black_queen: Bitboard,
+    pub black_king: Bitboard,
+    pub white_pawns: Bitboard,
+    pub white_rooks: Bitboard,
+    pub white_knights: Bitboard,
+    pub white_bishops: Bitboard,
+    pub white_queen: Bitboard,
+    pub white_king: Bitboard,
 }
 
 impl Display for Chessboard {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut fen = String::new();
 
-        for rank in (1..=8).rev() {
+        for rank in (0..8).rev() {
             let mut blank = 0;
-            for file in 'A'..='H' {
+            for file in 0..8 {
                 if let Some(piece) = self.piece_at_position(
-                    rank_file_to_square(rank, file).expect("Expected rank 1-8 file A-H"),
+                    Square::from_rank_file(rank, file).expect("Expected rank and file 0-7"),
                 ) {
                     if blank > 0 {
                         fen.push_str(&blank.to_string());
                         blank = 0;
                     }
-                    fen.push(piece);
+                    fen.push(piece.to_char());
                 } else {
                     blank += 1;
                 }
@@ -39,7 +40,7 @@ impl Display for Chessboard {
             if blank > 0 {
                 fen.push_str(&blank.to_string());
             }
-            if rank > 1 {
+            if rank > 0 {
                 fen.push('/');
             }
         }
@@ -47,38 +48,118 @@ impl Display for Chessboard {
     }
 }
 
+impl Default for Chessboard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Chessboard {
     /// piece placement portion of the FEN string
-    pub fn from_string(piece_placement: &str) -> Result<Self, String> {
-        let mut result = Chessboard::empty();
+    /// Columns in errors count from the start of `piece_placement`
+    pub fn from_string(piece_placement: &str) -> Result<Self, Error> {
+        let (board, mut errors) = Self::parse(piece_placement, FenMode::Lenient);
+        if errors.is_empty() {
+            Ok(board)
+        } else {
+            Err(errors.remove(0))
+        }
+    }
 
-        let mut rank_ndx = 8;
+    /// The board along with every problem found in `piece_placement`
+    /// Strict mode also wants 8 ranks of 8 files, one king each and no pawns on the back ranks
+    pub(crate) fn parse(piece_placement: &str, mode: FenMode) -> (Self, Vec<Error>) {
+        let mut result = Chessboard::empty();
+        let mut errors = Vec::new();
+        let strict = mode == FenMode::Strict;
+        let error = |column, reason| Error::Fen {
+            field: FenField::Placement,
+            column,
+            reason,
+        };
+
+        // Columns of the pieces strict mode checks once the number of ranks is known
+        let mut kings: [Vec<usize>; 2] = [Vec::new(), Vec::new()];
+        let mut back_rank_pawns = Vec::new();
+
+        let mut column = 0;
+        let mut ranks = 0;
         for pieces in piece_placement.split('/') {
-            let mut file_ndx = b'A';
+            let rank_column = column + 1;
+            let rank = 7_usize.checked_sub(ranks).map(|r| r as u8);
+            let mut files: u32 = 0;
+            ranks += 1;
+
+            for c in pieces.chars() {
+                column += 1;
+                if let Some(skip) = c.to_digit(10) {
+                    files += skip;
+                    continue;
+                }
 
-            for piece in pieces.chars() {
-                if piece.is_ascii_digit() {
-                    file_ndx += piece.to_digit(10).expect("Already checked") as u8;
+                let Some(piece) = Piece::from_char(c) else {
+                    errors.push(error(column, format!("unknown piece {c}")));
+                    files += 1;
                     continue;
+                };
+                match piece.kind {
+                    PieceKind::King => kings[piece.color as usize].push(column),
+                    PieceKind::Pawn if rank == Some(0) || rank == Some(7) => {
+                        back_rank_pawns.push(column)
+                    }
+                    _ => (),
                 }
+                let square = rank.and_then(|r| Square::from_rank_file(r, files.min(8) as u8));
+                files += 1;
+                let Some(square) = square else {
+                    // Strict mode reports the rank as a whole below
+                    if !strict {
+                        errors.push(error(column, format!("{c} is off the board")));
+                    }
+                    continue;
+                };
+                result.put_piece(square, piece);
+            }
+            column += 1; // the '/'
+
+            if strict && files != 8 {
+                errors.push(error(
+                    rank_column,
+                    format!("rank has {files} files instead of 8"),
+                ));
+            }
+        }
 
-                let square = rank_file_to_square(rank_ndx, file_ndx as char)?;
-                *result.piece_bitboard(piece)? |= 1 << square;
-                file_ndx += 1;
+        if strict {
+            if ranks == 8 {
+                for column in back_rank_pawns {
+                    errors.push(error(column, "pawn on the back rank".to_string()));
+                }
+            } else {
+                errors.push(error(1, format!("{ranks} ranks instead of 8")));
+            }
+            for color in [Color::White, Color::Black] {
+                match kings[color as usize].as_slice() {
+                    [] => errors.push(error(1, format!("no {color} king"))),
+                    [_, extra @ ..] => {
+                        for &column in extra {
+                            errors.push(error(column, format!("more than one {color} king")));
+                        }
+                    }
+                }
             }
-            rank_ndx -= 1;
         }
 
-        Ok(result)
+        (result, errors)
     }
 
     pub fn new() -> Chessboard {
-        let pawns = 0xFF;
-        let rooks = 0x81;
-        let knights = 0x42;
-        let bishops = 0x24;
-        let queen = 0x08;
-        let king = 0x10;
+        let pawns = Bitboard(0xFF);
+        let rooks = Bitboard(0x81);
+        let knights = Bitboard(0x42);
+        let bishops = Bitboard(0x24);
+        let queen = Bitboard(0x08);
+        let king = Bitboard(0x10);
 
         let top_row = 56; // 7 rows * 8 bits
 
@@ -101,19 +182,19 @@ impl Chessboard {
 
     pub fn empty() -> Self {
         Self {
-            white_rooks: 0,
-            white_knights: 0,
-            white_bishops: 0,
-            white_pawns: 0,
-            white_queen: 0,
-            white_king: 0,
-
-            black_rooks: 0,
-            black_knights: 0,
-            black_bishops: 0,
-            black_queen: 0,
-            black_king: 0,
-            black_pawns: 0,
+            white_rooks: Bitboard::EMPTY,
+            white_knights: Bitboard::EMPTY,
+            white_bishops: Bitboard::EMPTY,
+            white_pawns: Bitboard::EMPTY,
+            white_queen: Bitboard::EMPTY,
+            white_king: Bitboard::EMPTY,
+
+            black_rooks: Bitboard::EMPTY,
+            black_knights: Bitboard::EMPTY,
+            black_bishops: Bitboard::EMPTY,
+            black_queen: Bitboard::EMPTY,
+            black_king: Bitboard::EMPTY,
+            black_pawns: Bitboard::EMPTY,
         }
     }
 }
@@ -0,0 +1,107 @@
+use std::fmt::{self, Display};
+
+use crate::{PieceKind, Square};
+
+/// Everything the engine can reject, so callers can match on what went wrong
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// A FEN string that couldn't be read, `column` counts characters from 1
+    Fen {
+        field: FenField,
+        column: usize,
+        reason: String,
+    },
+    /// Text that doesn't name a square, ex: `i9`
+    InvalidSquare(String),
+    /// A letter that isn't a piece
+    InvalidPiece(char),
+    /// Text that isn't a move in any notation we read, ex: `e2`
+    InvalidMoveFormat(String),
+    IllegalMove {
+        from: Square,
+        to: Square,
+        reason: IllegalMove,
+    },
+}
+
+/// The six space separated parts of a FEN string
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FenField {
+    Placement,
+    SideToMove,
+    Castling,
+    EnPassant,
+    HalfMoveClock,
+    FullMoveNumber,
+    Trailing, // anything after the six fields
+}
+
+/// Why `GameState::move_piece_legally` refused a move
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IllegalMove {
+    NoPiece,
+    OutOfTurn,
+    NotReachable, // the piece can't get there, or doing so leaves its king in check
+    MissingPromotion,
+    NotPromotion,
+    InvalidPromotion(PieceKind),
+}
+
+impl Error {
+    /// Move the column of a FEN error along, for parts parsed apart from the rest
+    pub(crate) fn shift_column(self, by: usize) -> Self {
+        match self {
+            Error::Fen {
+                field,
+                column,
+                reason,
+            } => Error::Fen {
+                field,
+                column: column + by,
+                reason,
+            },
+            other => other,
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl Display for FenField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FenField::Placement => write!(f, "piece placement"),
+            FenField::SideToMove => write!(f, "side to move"),
+            FenField::Castling => write!(f, "castling rights"),
+            FenField::EnPassant => write!(f, "en passant square"),
+            FenField::HalfMoveClock => write!(f, "halfmove clock"),
+            FenField::FullMoveNumber => write!(f, "fullmove number"),
+            FenField::Trailing => write!(f, "trailing text"),
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Fen {
+                field,
+                column,
+                reason,
+            } => write!(f, "Invalid {field} at column {column}: {reason}"),
+            Error::InvalidSquare(text) => write!(f, "Invalid square: {text}"),
+            Error::InvalidPiece(c) => write!(f, "Invalid piece: {c}"),
+            Error::InvalidMoveFormat(text) => write!(f, "Invalid move format: {text}"),
+            Error::IllegalMove { from, to, reason } => match reason {
+                IllegalMove::NoPiece => write!(f, "No piece on {from}"),
+                IllegalMove::OutOfTurn => write!(f, "{from} -> {to} out of turn"),
+                IllegalMove::NotReachable => write!(f, "{from} -> {to} illegal move"),
+                IllegalMove::MissingPromotion => {
+                    write!(f, "{from} -> {to} needs a promotion piece")
+                }
+                IllegalMove::NotPromotion => write!(f, "{from} -> {to} is not a promotion"),
+                IllegalMove::InvalidPromotion(p) => write!(f, "Invalid promotion piece: {p}"),
+            },
+        }
+    }
+}
@@ -0,0 +1,340 @@
+use crate::pawns::{PawnTable, PawnWeights, Weight};
+use crate::{Chessboard, Color, GameState, PieceKind, Square};
+
+// https://www.chessprogramming.org/PeSTO%27s_Evaluation_Function
+// Tables are laid out as seen from white's side, A8 first and H1 last
+
+/// Middlegame and endgame worth of each piece type in centipawns, indexed by `PieceKind`
+const MG_VALUE: [i64; 6] = [82, 337, 365, 477, 1025, 0];
+const EG_VALUE: [i64; 6] = [94, 281, 297, 512, 936, 0];
+/// How much each piece type counts towards the middlegame
+const PHASE_WEIGHT: [i64; 6] = [0, 1, 1, 2, 4, 0];
+/// Phase of the starting position, 4 minors + 4 rooks * 2 + 2 queens * 4
+pub const MAX_PHASE: i64 = 24;
+
+#[rustfmt::skip]
+const MG_PAWN: [i64; 64] = [
+      0,   0,   0,   0,   0,   0,   0,   0,
+     98, 134,  61,  95,  68, 126,  34, -11,
+     -6,   7,  26,  31,  65,  56,  25, -20,
+    -14,  13,   6,  21,  23,  12,  17, -23,
+    -27,  -2,  -5,  12,  17,   6,  10, -25,
+    -26,  -4,  -4, -10,   3,   3,  33, -12,
+    -35,  -1, -20, -23, -15,  24,  38, -22,
+      0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const EG_PAWN: [i64; 64] = [
+      0,   0,   0,   0,   0,   0,   0,   0,
+    178, 173, 158, 134, 147, 132, 165, 187,
+     94, 100,  85,  67,  56,  53,  82,  84,
+     32,  24,  13,   5,  -2,   4,  17,  17,
+     13,   9,  -3,  -7,  -7,  -8,   3,  -1,
+      4,   7,  -6,   1,   0,  -5,  -1,  -8,
+     13,   8,   8,  10,  13,   0,   2,  -7,
+      0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const MG_KNIGHT: [i64; 64] = [
+    -167, -89, -34, -49,  61, -97, -15, -107,
+     -73, -41,  72,  36,  23,  62,   7,  -17,
+     -47,  60,  37,  65,  84, 129,  73,   44,
+      -9,  17,  19,  53,  37,  69,  18,   22,
+     -13,   4,  16,  13,  28,  19,  21,   -8,
+     -23,  -9,  12,  10,  19,  17,  25,  -16,
+     -29, -53, -12,  -3,  -1,  18, -14,  -19,
+    -105, -21, -58, -33, -17, -28, -19,  -23,
+];
+
+#[rustfmt::skip]
+const EG_KNIGHT: [i64; 64] = [
+    -58, -38, -13, -28, -31, -27, -63, -99,
+    -25,  -8, -25,  -2,  -9, -25, -24, -52,
+    -24, -20,  10,   9,  -1,  -9, -19, -41,
+    -17,   3,  22,  22,  22,  11,   8, -18,
+    -18,  -6,  16,  25,  16,  17,   4, -18,
+    -23,  -3,  -1,  15,  10,  -3, -20, -22,
+    -42, -20, -10,  -5,  -2, -20, -23, -44,
+    -29, -51, -23, -15, -22, -18, -50, -64,
+];
+
+#[rustfmt::skip]
+const MG_BISHOP: [i64; 64] = [
+    -29,   4, -82, -37, -25, -42,   7,  -8,
+    -26,  16, -18, -13,  30,  59,  18, -47,
+    -16,  37,  43,  40,  35,  50,  37,  -2,
+     -4,   5,  19,  50,  37,  37,   7,  -2,
+     -6,  13,  13,  26,  34,  12,  10,   4,
+      0,  15,  15,  15,  14,  27,  18,  10,
+      4,  15,  16,   0,   7,  21,  33,   1,
+    -33,  -3, -14, -21, -13, -12, -39, -21,
+];
+
+#[rustfmt::skip]
+const EG_BISHOP: [i64; 64] = [
+    -14, -21, -11,  -8,  -7,  -9, -17, -24,
+     -8,  -4,   7, -12,  -3, -13,  -4, -14,
+      2,  -8,   0,  -1,  -2,   6,   0,   4,
+     -3,   9,  12,   9,  14,  10,   3,   2,
+     -6,   3,  13,  19,   7,  10,  -3,  -9,
+    -12,  -3,   8,  10,  13,   3,  -7, -15,
+    -14, -18,  -7,  -1,   4,  -9, -15, -27,
+    -23,  -9, -23,  -5,  -9, -16,  -5, -17,
+];
+
+#[rustfmt::skip]
+const MG_ROOK: [i64; 64] = [
+     32,  42,  32,  51,  63,   9,  31,  43,
+     27,  32,  58,  62,  80,  67,  26,  44,
+     -5,  19,  26,  36,  17,  45,  61,  16,
+    -24, -11,   7,  26,  24,  35,  -8, -20,
+    -36, -26, -12,  -1,   9,  -7,   6, -23,
+    -45, -25, -16, -17,   3,   0,  -5, -33,
+    -44, -16, -20,  -9,  -1,  11,  -6, -71,
+    -19, -13,   1,  17,  16,   7, -37, -26,
+];
+
+#[rustfmt::skip]
+const EG_ROOK: [i64; 64] = [
+     13,  10,  18,  15,  12,  12,   8,   5,
+     11,  13,  13,  11,  -3,   3,   8,   3,
+      7,   7,   7,   5,   4,  -3,  -5,  -3,
+      4,   3,  13,   1,   2,   1,  -1,   2,
+      3,   5,   8,   4,  -5,  -6,  -8, -11,
+     -4,   0,  -5,  -1,  -7, -12,  -8, -16,
+     -6,  -6,   0,   2,  -9,  -9, -11,  -3,
+     -9,   2,   3,  -1,  -5, -13,   4, -20,
+];
+
+#[rustfmt::skip]
+const MG_QUEEN: [i64; 64] = [
+    -28,   0,  29,  12,  59,  44,  43,  45,
+    -24, -39,  -5,   1, -16,  57,  28,  54,
+    -13, -17,   7,   8,  29,  56,  47,  57,
+    -27, -27, -16, -16,  -1,  17,  -2,   1,
+     -9, -26,  -9, -10,  -2,  -4,   3,  -3,
+    -14,   2, -11,  -2,  -5,   2,  14,   5,
+    -35,  -8,  11,   2,   8,  15,  -3,   1,
+     -1, -18,  -9,  10, -15, -25, -31, -50,
+];
+
+#[rustfmt::skip]
+const EG_QUEEN: [i64; 64] = [
+     -9,  22,  22,  27,  27,  19,  10,  20,
+    -17,  20,  32,  41,  58,  25,  30,   0,
+    -20,   6,   9,  49,  47,  35,  19,   9,
+      3,  22,  24,  45,  57,  40,  57,  36,
+    -18,  28,  19,  47,  31,  34,  39,  23,
+    -16, -27,  15,   6,   9,  17,  10,   5,
+    -22, -23, -30, -16, -16, -23, -36, -32,
+    -33, -28, -22, -43,  -5, -32, -20, -41,
+];
+
+#[rustfmt::skip]
+const MG_KING: [i64; 64] = [
+    -65,  23,  16, -15, -56, -34,   2,  13,
+     29,  -1, -20,  -7,  -8,  -4, -38, -29,
+     -9,  24,   2, -16, -20,   6,  22, -22,
+    -17, -20, -12, -27, -30, -25, -14, -36,
+    -49,  -1, -27, -39, -46, -44, -33, -51,
+    -14, -14, -22, -46, -44, -30, -15, -27,
+      1,   7,  -8, -64, -43, -16,   9,   8,
+    -15,  36,  12, -54,   8, -28,  24,  14,
+];
+
+#[rustfmt::skip]
+const EG_KING: [i64; 64] = [
+    -74, -35, -18, -18, -11,  15,   4, -17,
+    -12,  17,  14,  17,  17,  38,  23,  11,
+     10,  17,  23,  15,  20,  45,  44,  13,
+     -8,  22,  24,  27,  26,  33,  26,   3,
+    -18,  -4,  21,  24,  27,  23,   9, -11,
+    -19,  -3,  11,  21,  23,  16,   7,  -9,
+    -27, -11,   4,  13,  14,   4,  -5, -17,
+    -53, -34, -21, -11, -28, -14, -24, -43,
+];
+
+const MG_TABLES: [[i64; 64]; 6] = [MG_PAWN, MG_KNIGHT, MG_BISHOP, MG_ROOK, MG_QUEEN, MG_KING];
+const EG_TABLES: [[i64; 64]; 6] = [EG_PAWN, EG_KNIGHT, EG_BISHOP, EG_ROOK, EG_QUEEN, EG_KING];
+
+/// Index into the tables, which are drawn upside down and from white's side
+fn table_square(square: Square, color: Color) -> usize {
+    match color {
+        Color::White => (square.index() ^ 56) as usize,
+        Color::Black => square.index() as usize,
+    }
+}
+
+/// Material worth of a piece of either colour in centipawns, the king isn't counted
+pub(crate) fn piece_value(kind: PieceKind) -> i64 {
+    MG_VALUE[kind as usize]
+}
+
+impl Chessboard {
+    /// Determine who is winning, in centipawns
+    /// A positive number indicates white is winning
+    /// Material, piece placement and pawn structure are scored for both the middlegame
+    /// and the endgame, then blended by how much material is left
+    /// Mobility and king safety need move generation, see `GameState::evaluate`
+    pub fn evaluate(&self, weights: &PawnWeights) -> i64 {
+        self.blend(self.pawn_structure(weights))
+    }
+
+    /// Add material and placement to the other terms of the evaluation and taper it
+    fn blend(&self, (extra_mg, extra_eg): Weight) -> i64 {
+        let (mut mg, mut eg) = (extra_mg, extra_eg);
+
+        for (piece, board) in self.piece_bitboards() {
+            let kind = piece.kind as usize;
+            let sign = piece.color.sign();
+
+            for square in board {
+                let square = table_square(square, piece.color);
+                mg += sign * (MG_VALUE[kind] + MG_TABLES[kind][square]);
+                eg += sign * (EG_VALUE[kind] + EG_TABLES[kind][square]);
+            }
+        }
+
+        let phase = self.game_phase();
+        (mg * phase + eg * (MAX_PHASE - phase)) / MAX_PHASE
+    }
+
+    /// How far from the endgame the position is, from 0 (bare kings and pawns)
+    /// to `MAX_PHASE` (all pieces on the board)
+    pub fn game_phase(&self) -> i64 {
+        let phase: i64 = self
+            .piece_bitboards()
+            .iter()
+            .map(|(piece, board)| PHASE_WEIGHT[piece.kind as usize] * board.count() as i64)
+            .sum();
+        // Promotions can push it past the start
+        phase.min(MAX_PHASE)
+    }
+}
+
+impl GameState {
+    /// The full evaluation in centipawns, positive when white is winning
+    /// `Chessboard::evaluate` plus mobility and king safety, with the pawn structure
+    /// looked up in `pawns` when it can
+    pub fn evaluate(&self, pawns: &mut PawnTable) -> i64 {
+        let terms = [
+            pawns.pawn_structure(self),
+            self.mobility(),
+            self.king_safety(),
+        ];
+        let (mg, eg) = terms.iter().fold((0, 0), |(mg, eg), (term_mg, term_eg)| {
+            (mg + term_mg, eg + term_eg)
+        });
+        self.board.blend((mg, eg))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Swap the colours and flip the board vertically
+    fn mirror(placement: &str) -> String {
+        let ranks: Vec<String> = placement
+            .split('/')
+            .rev()
+            .map(|rank| {
+                rank.chars()
+                    .map(|c| {
+                        if c.is_ascii_uppercase() {
+                            c.to_ascii_lowercase()
+                        } else {
+                            c.to_ascii_uppercase()
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        ranks.join("/")
+    }
+
+    /// Evaluate a piece placement with the default weights
+    fn evaluate(placement: &str) -> i64 {
+        let board = Chessboard::from_string(placement).unwrap();
+        board.evaluate(&PawnWeights::default())
+    }
+
+    #[test]
+    fn test_evaluate() {
+        assert_eq!(evaluate("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR"), 0);
+
+        // A pawn up is worth roughly a pawn
+        assert!((50..200).contains(&evaluate("4k3/8/8/8/8/8/P7/4K3")));
+    }
+
+    #[test]
+    fn test_evaluate_symmetric() {
+        for placement in [
+            "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8",
+        ] {
+            assert_eq!(
+                evaluate(placement),
+                -evaluate(&mirror(placement)),
+                "{placement}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_piece_placement() {
+        assert!(evaluate("4k3/8/8/4N3/8/8/8/4K3") > evaluate("4k3/8/8/8/8/8/8/N3K3"));
+
+        // The king hides in the middlegame and comes out in the endgame
+        assert!(
+            evaluate("rnbq1rk1/8/8/8/8/8/8/RNBQ1RK1") > evaluate("rnbq1rk1/8/8/8/4K3/8/8/RNBQ1R2")
+        );
+        assert!(evaluate("6k1/8/8/8/8/8/8/6K1") < evaluate("6k1/8/8/8/4K3/8/8/8"));
+    }
+
+    #[test]
+    fn test_pawn_weights() {
+        // Only white has a passed pawn, so making them worth more favours white
+        let weights = PawnWeights {
+            passed: [(300, 300); 8],
+            ..PawnWeights::default()
+        };
+        let board = Chessboard::from_string("4k3/8/8/3P4/8/8/8/4K3").unwrap();
+        assert!(board.evaluate(&weights) > board.evaluate(&PawnWeights::default()) + 200);
+
+        let gs = GameState::from_string("4k3/8/8/3P4/8/8/8/4K3 w - - 0 1").unwrap();
+        let default = gs.evaluate(&mut PawnTable::default());
+        assert!(gs.evaluate(&mut PawnTable::new(1, weights)) > default + 200);
+    }
+
+    #[test]
+    fn test_full_evaluation() {
+        let gs = GameState::from_string(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        )
+        .unwrap();
+        let mut pawns = PawnTable::default();
+        let score = gs.evaluate(&mut pawns);
+        assert_eq!(
+            gs.evaluate(&mut pawns),
+            score,
+            "Cached pawn structure changed the score"
+        );
+
+        let mirrored = format!("{} w - - 0 1", mirror(&gs.board.to_string()));
+        let mirrored = GameState::from_string(&mirrored).unwrap();
+        assert_eq!(mirrored.evaluate(&mut PawnTable::default()), -score);
+    }
+
+    #[test]
+    fn test_game_phase() {
+        assert_eq!(Chessboard::new().game_phase(), MAX_PHASE);
+        let endgame = Chessboard::from_string("4k3/pppp4/8/8/8/8/PPPP4/4K3").unwrap();
+        assert_eq!(endgame.game_phase(), 0);
+        let rooks = Chessboard::from_string("r3k3/8/8/8/8/8/8/R3K3").unwrap();
+        assert_eq!(rooks.game_phase(), 4);
+    }
+}
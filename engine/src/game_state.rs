@@ -1,17 +1,35 @@
 use core::fmt;
 use std::fmt::Display;
 
+use crate::make_move::Undo;
 use crate::position;
-use crate::Chessboard;
+use crate::{CastlingRights, Chessboard, Color, Error, FenField, Piece, PieceKind, Square};
 
 // https://www.chess.com/terms/fen-chess
+
+#[derive(Clone)]
 pub struct GameState {
-    white_turn: bool,
-    pub castling: u8,   // KQkq will be represented by 4 bits
-    pub en_passant: u8, // a square that has en passant ability
-    half_clock: u32,
-    move_count: u32,
+    pub(crate) side_to_move: Color,
+    pub castling: CastlingRights,
+    pub en_passant: Option<Square>, // a square that has en passant ability
+    pub(crate) half_clock: u32,
+    pub(crate) move_count: u32,
     pub board: Chessboard,
+    pub(crate) history: Vec<Undo>, // moves played, newest last
+    pub(crate) hash: u64,          // Zobrist key of the position
+    pub(crate) pawn_hash: u64,     // Zobrist key of just the pawns
+}
+
+/// How closely `GameState::from_fen` checks its input
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FenMode {
+    /// Only rejects what can't be read, and accepts 4 field EPD style strings
+    /// whose clocks are then taken to be 0 and 1
+    Lenient,
+    /// Also rejects positions that can't happen: ranks that aren't 8 files long,
+    /// anything but 8 ranks, unknown castling letters, a missing or extra king,
+    /// pawns on the first or last rank and castling rights whose king or rook has moved
+    Strict,
 }
 
 impl Default for GameState {
@@ -23,23 +41,17 @@ impl Default for GameState {
 impl Display for GameState {
     #[rustfmt::skip]
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let color = if self.white_turn { 'w' } else { 'b' };
-
-        let mut castles = String::new();
-        if self.castling & 0b1000 != 0 { castles.push('K'); }
-        if self.castling & 0b0100 != 0 { castles.push('Q'); }
-        if self.castling & 0b0010 != 0 { castles.push('k'); }
-        if self.castling & 0b0001 != 0 { castles.push('q'); }
-        if castles.is_empty() { castles.push('-'); }
+        let color = if self.side_to_move.is_white() { 'w' } else { 'b' };
 
-        let en_passant = if self.en_passant > 63 { "-".to_string() } else {
-            format!("{}{}", position::square_to_file(self.en_passant), position::square_to_rank(self.en_passant))
+        let en_passant = match self.en_passant {
+            None => "-".to_string(),
+            Some(square) => square.to_string(),
         };
 
         write!(
             f,
             "{} {} {} {} {} {}",
-            self.board, color, castles, en_passant, self.half_clock, self.move_count
+            self.board, color, self.castling, en_passant, self.half_clock, self.move_count
         )
     }
 }
@@ -52,55 +64,212 @@ impl fmt::Debug for GameState {
 
 impl GameState {
     pub fn new() -> Self {
-        Self {
-            white_turn: true,
-            castling: 0x0F,
-            en_passant: 255,
+        let mut gs = Self {
+            side_to_move: Color::White,
+            castling: CastlingRights::ALL,
+            en_passant: None,
             half_clock: 0,
             move_count: 1,
             board: Chessboard::new(),
+            history: Vec::new(),
+            hash: 0,
+            pawn_hash: 0,
+        };
+        gs.hash = gs.compute_hash();
+        gs.pawn_hash = gs.compute_pawn_hash();
+        gs
+    }
+
+    pub fn side_to_move(&self) -> Color {
+        self.side_to_move
+    }
+
+    /// Full move number, starting at 1 and increasing after black moves
+    pub fn move_count(&self) -> u32 {
+        self.move_count
+    }
+
+    /// Read a FEN string leniently, see `FenMode::Lenient`
+    pub fn from_string(fen: &str) -> Result<Self, Error> {
+        Self::from_fen(fen, FenMode::Lenient).map_err(|mut errors| errors.remove(0))
+    }
+
+    /// Read a FEN string, listing every problem found rather than stopping at the first
+    pub fn from_fen(fen: &str, mode: FenMode) -> Result<Self, Vec<Error>> {
+        let parts = fields(fen);
+        let mut errors = Vec::new();
+        let error = |index: usize, reason: String| Error::Fen {
+            field: FIELDS[index],
+            column: parts
+                .get(index)
+                .map_or(fen.chars().count() + 1, |(c, _)| *c),
+            reason,
+        };
+
+        let epd = mode == FenMode::Lenient && parts.len() == 4;
+        if parts.len() < 6 && !epd {
+            errors.push(error(parts.len(), "missing".to_string()));
+        }
+        if parts.len() > 6 {
+            errors.push(error(
+                6,
+                "nothing may follow the fullmove number".to_string(),
+            ));
         }
+        let text = |index: usize| parts.get(index).map(|(_, t)| *t);
+        let mut number = |index: usize, default: u32| match text(index).map(str::parse) {
+            None => default,
+            Some(Ok(n)) => n,
+            Some(Err(_)) => {
+                errors.push(error(
+                    index,
+                    format!("{} is not a number", text(index).unwrap()),
+                ));
+                default
+            }
+        };
+        let half_clock = number(4, 0);
+        let move_count = number(5, 1);
+
+        let (board, placement_errors): (_, Vec<_>) = match parts.first() {
+            Some(&(column, placement)) => {
+                let (board, found) = Chessboard::parse(placement, mode);
+                let found = found.into_iter().map(|e| e.shift_column(column - 1));
+                (board, found.collect())
+            }
+            None => (Chessboard::empty(), Vec::new()),
+        };
+        // Castling rights can only be checked against a board that makes sense
+        let trusted_board = placement_errors.is_empty().then_some(&board);
+        errors.extend(placement_errors);
+
+        let side_to_move = match text(1) {
+            Some("w") | None => Color::White,
+            Some("b") => Color::Black,
+            Some(other) => {
+                errors.push(error(1, format!("{other} is not w or b")));
+                Color::White
+            }
+        };
+
+        let castling = text(2).map_or(CastlingRights::NONE, parse_castling_rights);
+        if mode == FenMode::Strict {
+            if let Some(&(column, part)) = parts.get(2) {
+                errors.extend(castling_problems(part, column, trusted_board));
+            }
+        }
+
+        let en_passant = match text(3) {
+            Some("-") | None => None,
+            Some(square) => match position::string_to_square(square) {
+                Ok(square) => Some(square),
+                Err(_) => {
+                    errors.push(error(3, format!("{square} is not a square")));
+                    None
+                }
+            },
+        };
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        let mut gs = Self {
+            side_to_move,
+            castling,
+            en_passant,
+            half_clock,
+            move_count,
+            board,
+            history: Vec::new(),
+            hash: 0,
+            pawn_hash: 0,
+        };
+        gs.hash = gs.compute_hash();
+        gs.pawn_hash = gs.compute_pawn_hash();
+        Ok(gs)
     }
+}
 
-    pub fn from_string(fen: &str) -> Result<Self, String> {
-        let parts: Vec<&str> = fen.split_whitespace().collect();
-        if parts.len() != 6 {
-            return Err("Invalid FEN string".to_string());
+/// In the order they appear in a FEN string
+const FIELDS: [FenField; 7] = [
+    FenField::Placement,
+    FenField::SideToMove,
+    FenField::Castling,
+    FenField::EnPassant,
+    FenField::HalfMoveClock,
+    FenField::FullMoveNumber,
+    FenField::Trailing,
+];
+
+/// Each whitespace separated field with the column it starts at, counted from 1
+fn fields(fen: &str) -> Vec<(usize, &str)> {
+    let mut result = Vec::new();
+    let mut start = None;
+    for (column, (index, c)) in fen.char_indices().enumerate() {
+        match (c.is_whitespace(), start) {
+            (false, None) => start = Some((column + 1, index)),
+            (true, Some((first, begin))) => {
+                result.push((first, &fen[begin..index]));
+                start = None;
+            }
+            _ => (),
         }
+    }
+    if let Some((first, begin)) = start {
+        result.push((first, &fen[begin..]));
+    }
+    result
+}
+
+/// Unknown letters in the castling field, and rights whose king or rook has left its square
+/// `column` is where the field starts, pieces are only checked when there is a `board`
+fn castling_problems(part: &str, column: usize, board: Option<&Chessboard>) -> Vec<Error> {
+    let mut problems = Vec::new();
+    if part == "-" {
+        return problems;
+    }
 
-        let passant = match parts[3] {
-            "-" => 255, // out of range
-            _ => position::string_to_square(parts[3])
-                .map_err(|e| format!("Invalid en passant: {e}"))?,
+    for (offset, c) in part.chars().enumerate() {
+        let error = |reason| Error::Fen {
+            field: FenField::Castling,
+            column: column + offset,
+            reason,
+        };
+        let (color, rook) = match c {
+            'K' => (Color::White, Square::H1),
+            'Q' => (Color::White, Square::A1),
+            'k' => (Color::Black, Square::H8),
+            'q' => (Color::Black, Square::A8),
+            _ => {
+                problems.push(error(format!("unknown castling right {c}")));
+                continue;
+            }
+        };
+        let king = if color.is_white() {
+            Square::E1
+        } else {
+            Square::E8
         };
 
-        Ok(Self {
-            white_turn: parts[1] == "w",
-            castling: parse_castling_rights(parts[2]),
-            en_passant: passant,
-            half_clock: parts[4]
-                .parse()
-                .map_err(|_| "Invalid half clock".to_string())?,
-            move_count: parts[5]
-                .parse()
-                .map_err(|_| "Invalid move count".to_string())?,
-            board: Chessboard::from_string(parts[0])?,
-        })
+        let Some(board) = board else {
+            continue;
+        };
+        let has = |square, kind| board.piece_at_position(square) == Some(Piece::new(color, kind));
+        if !has(king, PieceKind::King) || !has(rook, PieceKind::Rook) {
+            problems.push(error(format!(
+                "{c} needs the king on {king} and a rook on {rook}"
+            )));
+        }
     }
+    problems
 }
 
 /// part: The portion of the fen string that marks castling
-fn parse_castling_rights(part: &str) -> u8 {
-    let mut result = 0;
-    for c in part.chars() {
-        let v = match c {
-            'K' => 0b1000,
-            'Q' => 0b0100,
-            'k' => 0b0010,
-            'q' => 0b0001,
-            _ => 0,
-        };
-        result |= v;
+fn parse_castling_rights(part: &str) -> CastlingRights {
+    let mut result = CastlingRights::NONE;
+    for right in part.chars().filter_map(CastlingRights::from_char) {
+        result |= right;
     }
     result
 }
@@ -114,9 +283,9 @@ mod tests {
         let fen_str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
         let fen = GameState::from_string(fen_str).unwrap();
 
-        assert!(fen.white_turn);
-        assert_eq!(fen.castling, 0b1111);
-        assert_eq!(fen.en_passant, 255);
+        assert_eq!(fen.side_to_move, Color::White);
+        assert_eq!(fen.castling, CastlingRights::ALL);
+        assert_eq!(fen.en_passant, None);
         assert_eq!(fen.half_clock, 0);
         assert_eq!(fen.move_count, 1);
     }
@@ -125,9 +294,9 @@ mod tests {
     fn test_passant() {
         let mut fen =
             GameState::from_string("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
-        assert_eq!(fen.unwrap().en_passant, 255);
+        assert_eq!(fen.unwrap().en_passant, None);
         fen = GameState::from_string("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e3 0 1");
-        assert_eq!(fen.unwrap().en_passant, 20);
+        assert_eq!(fen.unwrap().en_passant, Some(Square::E3));
         fen = GameState::from_string("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq i3 0 1");
         assert!(fen.is_err());
         fen = GameState::from_string("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e9 0 1");
@@ -139,16 +308,16 @@ mod tests {
         let mut fen =
             GameState::from_string("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1")
                 .unwrap();
-        assert_eq!(fen.castling, 0b1111);
+        assert_eq!(fen.castling, CastlingRights::ALL);
         fen = GameState::from_string("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w Kk - 0 1")
             .unwrap();
-        assert_eq!(fen.castling, 0b1010);
+        assert_eq!(fen.castling.to_string(), "Kk");
         fen = GameState::from_string("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w Qq - 0 1")
             .unwrap();
-        assert_eq!(fen.castling, 0b0101);
+        assert_eq!(fen.castling.to_string(), "Qq");
         fen = GameState::from_string("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1")
             .unwrap();
-        assert_eq!(fen.castling, 0);
+        assert_eq!(fen.castling, CastlingRights::NONE);
     }
 
     #[test]
@@ -160,6 +329,153 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_fen_error_location() {
+        let error = |fen| match GameState::from_string(fen) {
+            Err(Error::Fen { field, column, .. }) => (field, column),
+            other => panic!("Expected a FEN error for {fen}, got {other:?}"),
+        };
+
+        assert_eq!(
+            error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0"),
+            (FenField::FullMoveNumber, 55)
+        );
+        assert_eq!(
+            error("rnbqkbnr/ppppxppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"),
+            (FenField::Placement, 14)
+        );
+        assert_eq!(
+            error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e9 0 1"),
+            (FenField::EnPassant, 52)
+        );
+        assert_eq!(
+            error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR  w KQkq - x 1"),
+            (FenField::HalfMoveClock, 55)
+        );
+        assert_eq!(
+            error("8/8/8/8/8/8/8/8 w - - 0 1 extra"),
+            (FenField::Trailing, 27)
+        );
+        assert_eq!(
+            GameState::from_string("8/8/8/8/8/8/8/8 w - - 0 1 extra")
+                .unwrap_err()
+                .to_string(),
+            "Invalid trailing text at column 27: nothing may follow the fullmove number"
+        );
+    }
+
+    /// Field and column of every problem strict mode finds
+    fn strict_problems(fen: &str) -> Vec<(FenField, usize)> {
+        match GameState::from_fen(fen, FenMode::Strict) {
+            Ok(_) => Vec::new(),
+            Err(errors) => errors
+                .into_iter()
+                .map(|e| match e {
+                    Error::Fen { field, column, .. } => (field, column),
+                    other => panic!("Expected a FEN error, got {other:?}"),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_strict_fen() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2",
+        ] {
+            assert_eq!(strict_problems(fen), vec![], "{fen}");
+        }
+
+        let placement = FenField::Placement;
+        assert_eq!(
+            strict_problems("rnbqkbnr/ppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"),
+            vec![(placement, 10)],
+            "Rank with 7 files"
+        );
+        assert_eq!(
+            strict_problems("rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"),
+            vec![(placement, 19)],
+            "Rank with 9 files"
+        );
+        assert_eq!(
+            strict_problems("rnbqkbnr/pppppppp/8/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"),
+            vec![(placement, 1)],
+            "9 ranks"
+        );
+        assert_eq!(
+            strict_problems("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1"),
+            vec![(FenField::SideToMove, 45)]
+        );
+        assert_eq!(
+            strict_problems("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQxq - 0 1"),
+            vec![(FenField::Castling, 49)],
+            "Unknown castling letter"
+        );
+        assert_eq!(
+            strict_problems("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBKKBNR w kq - 0 1"),
+            vec![(placement, 40)],
+            "Two white kings"
+        );
+        assert_eq!(
+            strict_problems("rnbq1bnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQ - 0 1"),
+            vec![(placement, 1)],
+            "No black king"
+        );
+        assert_eq!(
+            strict_problems("rnbqkbnP/pppppppp/8/8/8/8/PPPPPPP1/RNBQKBNR w KQq - 0 1"),
+            vec![(placement, 8)],
+            "Pawn on the eighth rank"
+        );
+        assert_eq!(
+            strict_problems("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBN1 w KQkq - 0 1"),
+            vec![(FenField::Castling, 47)],
+            "White can't castle short without the H1 rook"
+        );
+        assert_eq!(
+            strict_problems("rnbq1bnr/ppppkppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"),
+            vec![(FenField::Castling, 49), (FenField::Castling, 50)],
+            "Black's king has moved"
+        );
+    }
+
+    #[test]
+    fn test_strict_fen_reports_everything() {
+        let errors = GameState::from_fen(
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPP/RNBQKKNR x KQkz - 0 1",
+            FenMode::Strict,
+        )
+        .unwrap_err();
+        assert_eq!(errors.len(), 4, "{errors:?}");
+        assert_eq!(
+            errors[0].to_string(),
+            "Invalid piece placement at column 27: rank has 7 files instead of 8"
+        );
+    }
+
+    #[test]
+    fn test_lenient_fen() {
+        // EPD style, without the clocks
+        let epd = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3";
+        let gs = GameState::from_string(epd).unwrap();
+        assert_eq!(gs.half_clock, 0);
+        assert_eq!(gs.move_count, 1);
+        assert_eq!(gs.en_passant, Some(Square::E3));
+        assert!(GameState::from_fen(epd, FenMode::Strict).is_err());
+
+        // Short ranks and a missing king are only a problem in strict mode
+        let sparse = "4k3/8/8/8/8/8/8/4 w - - 0 1";
+        assert!(GameState::from_string(sparse).is_ok());
+        assert!(GameState::from_fen(sparse, FenMode::Strict).is_err());
+
+        // Five fields is neither a FEN nor an EPD
+        assert!(
+            GameState::from_string("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0")
+                .is_err()
+        );
+    }
+
     #[test]
     fn test_to_string() {
         let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
@@ -0,0 +1,141 @@
+use crate::attacks;
+use crate::mobility::MOBILE_PIECES;
+use crate::pawns::{file_mask, neighbouring_files, Weight};
+use crate::{Bitboard, Color, GameState, Piece, PieceKind, Square};
+
+// https://www.chessprogramming.org/King_Safety
+// Only the middlegame is scored, in the endgame the king is meant to come out
+
+/// Bonus for each pawn right in front of the king, or one rank further
+const SHIELD_CLOSE: i64 = 12;
+const SHIELD_FAR: i64 = 6;
+/// Penalty for each file next to the king without a pawn of its own
+const SEMI_OPEN_FILE: i64 = -12;
+/// Penalty for each file next to the king without any pawns at all
+const OPEN_FILE: i64 = -25;
+/// How dangerous each attacker of the king zone is, indexed like `MOBILE_PIECES`
+const ATTACK_WEIGHT: [i64; 4] = [20, 20, 40, 80];
+/// Percentage of the attack weights that counts, by the number of attackers
+/// A lone attacker is harmless, a coordinated attack is not
+const ATTACKER_SCALE: [i64; 8] = [0, 0, 50, 75, 88, 94, 97, 99];
+
+/// The king's square and every square around it
+fn king_zone(square: Square) -> Bitboard {
+    attacks::king_attacks(square) | square.bitboard()
+}
+
+impl GameState {
+    /// Middlegame and endgame score for how well protected the kings are,
+    /// positive when white's is safer
+    pub fn king_safety(&self) -> Weight {
+        let mg = self.side_king_safety(Color::White) - self.side_king_safety(Color::Black);
+        (mg, 0)
+    }
+
+    fn side_king_safety(&self, color: Color) -> i64 {
+        let king = match self.king_square(color) {
+            Some(k) => k,
+            None => return 0,
+        };
+        let own_pawns = self.board.pieces(Piece::new(color, PieceKind::Pawn));
+        let enemy_pawns = self.board.pieces(Piece::new(!color, PieceKind::Pawn));
+        let files = file_mask(king) | neighbouring_files(king);
+        let mut score = 0;
+
+        // Pawn shield, only for a king that is still at home
+        let rank = king.rank();
+        let home_rank = if color.is_white() { rank } else { 7 - rank };
+        if home_rank <= 1 {
+            let (close, far) = if color.is_white() {
+                (
+                    files & Bitboard::rank(rank + 1),
+                    files & Bitboard::rank(rank + 2),
+                )
+            } else {
+                (
+                    files & Bitboard::rank(rank - 1),
+                    files & Bitboard::rank(rank - 2),
+                )
+            };
+            score += SHIELD_CLOSE * (own_pawns & close).count() as i64;
+            score += SHIELD_FAR * (own_pawns & far).count() as i64;
+        }
+
+        // Open lines towards the king
+        for file in king.file().saturating_sub(1)..=(king.file() + 1).min(7) {
+            let mask = Bitboard::file(file);
+            if (own_pawns & mask).is_empty() {
+                score += if (enemy_pawns & mask).is_empty() {
+                    OPEN_FILE
+                } else {
+                    SEMI_OPEN_FILE
+                };
+            }
+        }
+
+        // Enemy pieces bearing down on the squares around the king
+        let zone = king_zone(king);
+        let (mut attackers, mut weight) = (0, 0);
+        for (index, kind) in MOBILE_PIECES.into_iter().enumerate() {
+            let piece = Piece::new(!color, kind);
+            for square in self.board.pieces(piece) {
+                if !(self.piece_reach(square, piece) & zone).is_empty() {
+                    attackers += 1;
+                    weight += ATTACK_WEIGHT[index];
+                }
+            }
+        }
+        score -= weight * ATTACKER_SCALE[attackers.min(7)] / 100;
+
+        score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn king_safety(fen: &str) -> i64 {
+        GameState::from_string(fen).unwrap().king_safety().0
+    }
+
+    #[test]
+    fn test_king_zone() {
+        assert_eq!(king_zone(Square::A1), Bitboard(0x0303));
+        assert_eq!(king_zone(Square::H1), Bitboard(0xC0C0));
+        assert_eq!(king_zone(Square::H8).count(), 4);
+        assert_eq!(king_zone(Square::D4).count(), 9);
+    }
+
+    #[test]
+    fn test_pawn_shield() {
+        // Castled behind three pawns against a king that walked away from them
+        let castled = king_safety("6k1/5ppp/8/8/8/8/5PPP/6K1 w - - 0 1");
+        let exposed = king_safety("6k1/5ppp/8/8/8/8/5PPP/1K6 w - - 0 1");
+        assert_eq!(castled, 0);
+        assert!(exposed < castled);
+    }
+
+    #[test]
+    fn test_open_files() {
+        let white_safety = |fen| {
+            GameState::from_string(fen)
+                .unwrap()
+                .side_king_safety(Color::White)
+        };
+        let closed = white_safety("6k1/5ppp/8/8/8/8/5PPP/6K1 w - - 0 1");
+        let semi_open = white_safety("6k1/5ppp/8/8/8/8/5P1P/6K1 w - - 0 1");
+        let open = white_safety("6k1/5p1p/8/8/8/8/5P1P/6K1 w - - 0 1");
+        assert_eq!(closed, 3 * SHIELD_CLOSE);
+        assert_eq!(semi_open, 2 * SHIELD_CLOSE + SEMI_OPEN_FILE);
+        assert_eq!(open, 2 * SHIELD_CLOSE + OPEN_FILE);
+    }
+
+    #[test]
+    fn test_king_attackers() {
+        // One piece near the king isn't an attack yet, two are
+        let alone = king_safety("6k1/8/8/8/8/4n3/5PPP/6K1 w - - 0 1");
+        let together = king_safety("6k1/8/8/8/7q/4n3/5PPP/6K1 w - - 0 1");
+        assert_eq!(together - alone, -(20 + 80) / 2);
+    }
+}
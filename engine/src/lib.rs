@@ -1,8 +1,37 @@
+mod attacks; // precomputed attack tables
+mod bitboard; // sets of squares
+mod castling; // which castling moves are still allowed
+mod chess_move; // describing a single move
 mod chessboard; // board representation
+mod error; // what can go wrong
+mod evaluation; // scoring a position
 mod game_state; // FEN stuff
+mod king_safety; // pawn shields and attackers
+mod limits; // when to stop searching
+mod make_move; // Updating the state after a move
+mod mobility; // how freely pieces move
 mod move_generation;
+mod outcome; // checkmate, stalemate and draws
+mod pawns; // pawn structure
+mod perft; // move generation validation
+mod piece; // colours and piece types
+mod search; // picking the best move
+mod square; // board coordinates
 mod state; // Seeing where pieces already are // Seeing which pieces can go where
+mod transposition; // remembering searched positions
+mod zobrist; // position keys
 
 pub mod position;
+pub use bitboard::Bitboard;
+pub use castling::CastlingRights;
+pub use chess_move::Move;
 pub use chessboard::Chessboard;
-pub use game_state::GameState;
+pub use error::{Error, FenField, IllegalMove};
+pub use game_state::{FenMode, GameState};
+pub use limits::SearchLimits;
+pub use outcome::{DrawReason, GameResult};
+pub use pawns::{PawnTable, PawnWeights, Weight, DEFAULT_PAWN_HASH_MB};
+pub use piece::{Color, Piece, PieceKind};
+pub use search::{SearchResult, MATE, MAX_DEPTH};
+pub use square::Square;
+pub use transposition::{TranspositionTable, DEFAULT_HASH_MB, MAX_HASH_MB};
@@ -0,0 +1,127 @@
+use std::time::Duration;
+
+use crate::Color;
+
+/// Assume this many moves are left when the clock doesn't say
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+/// Time kept in reserve so communication lag never costs the game
+const SAFETY_MARGIN: Duration = Duration::from_millis(50);
+
+/// When a search should stop, any limit that is hit ends it
+/// Mirrors the options of the UCI `go` command
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SearchLimits {
+    pub depth: Option<u32>,
+    pub nodes: Option<u64>,
+    pub move_time: Option<Duration>, // exact time to spend on this move
+    pub white_time: Option<Duration>,
+    pub black_time: Option<Duration>,
+    pub white_increment: Duration,
+    pub black_increment: Duration,
+    pub moves_to_go: Option<u32>, // moves until the next time control
+    pub infinite: bool,           // only stop when told to
+}
+
+impl SearchLimits {
+    pub fn depth(depth: u32) -> Self {
+        Self {
+            depth: Some(depth),
+            ..Self::default()
+        }
+    }
+
+    /// How long the side to move may think, None if there is no time limit
+    pub fn time_budget(&self, color: Color) -> Option<Duration> {
+        if self.infinite {
+            return None;
+        }
+        if let Some(move_time) = self.move_time {
+            return Some(
+                move_time
+                    .saturating_sub(SAFETY_MARGIN)
+                    .max(Duration::from_millis(1)),
+            );
+        }
+
+        let (remaining, increment) = match color {
+            Color::White => (self.white_time?, self.white_increment),
+            Color::Black => (self.black_time?, self.black_increment),
+        };
+
+        let moves_to_go = self.moves_to_go.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+        let budget = remaining / moves_to_go + increment * 3 / 4;
+
+        // Never plan to use more than what is on the clock
+        let available = remaining.saturating_sub(SAFETY_MARGIN);
+        Some(budget.min(available).max(Duration::from_millis(1)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_time_limit() {
+        assert_eq!(SearchLimits::default().time_budget(Color::White), None);
+        assert_eq!(SearchLimits::depth(5).time_budget(Color::Black), None);
+
+        let limits = SearchLimits {
+            white_time: Some(Duration::from_secs(60)),
+            infinite: true,
+            ..SearchLimits::default()
+        };
+        assert_eq!(limits.time_budget(Color::White), None);
+    }
+
+    #[test]
+    fn test_move_time() {
+        let limits = SearchLimits {
+            move_time: Some(Duration::from_millis(1000)),
+            white_time: Some(Duration::from_secs(60)),
+            ..SearchLimits::default()
+        };
+        assert_eq!(
+            limits.time_budget(Color::White),
+            Some(Duration::from_millis(950))
+        );
+    }
+
+    #[test]
+    fn test_clock_budget() {
+        let limits = SearchLimits {
+            white_time: Some(Duration::from_secs(60)),
+            black_time: Some(Duration::from_secs(30)),
+            black_increment: Duration::from_secs(2),
+            ..SearchLimits::default()
+        };
+        assert_eq!(
+            limits.time_budget(Color::White),
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(
+            limits.time_budget(Color::Black),
+            Some(Duration::from_millis(2500))
+        );
+
+        let limits = SearchLimits {
+            white_time: Some(Duration::from_secs(10)),
+            moves_to_go: Some(1),
+            ..SearchLimits::default()
+        };
+        assert_eq!(
+            limits.time_budget(Color::White),
+            Some(Duration::from_millis(9950)),
+            "Last move before the time control keeps a safety margin"
+        );
+
+        let limits = SearchLimits {
+            black_time: Some(Duration::from_millis(20)),
+            ..SearchLimits::default()
+        };
+        assert_eq!(
+            limits.time_budget(Color::Black),
+            Some(Duration::from_millis(1))
+        );
+    }
+}
@@ -0,0 +1,382 @@
+use super::GameState;
+use crate::attacks;
+use crate::zobrist::{castling_key, en_passant_key, side_key};
+use crate::{CastlingRights, Move, Piece, PieceKind, Square};
+
+/// Piece types a pawn may promote to
+pub const PROMOTIONS: [PieceKind; 4] = [
+    PieceKind::Queen,
+    PieceKind::Rook,
+    PieceKind::Bishop,
+    PieceKind::Knight,
+];
+
+/// Castling rights lost when a piece leaves or lands on `square`
+#[rustfmt::skip]
+fn castling_rights_lost(square: Square) -> CastlingRights {
+    match square {
+        Square::A1 => CastlingRights::WHITE_QUEENSIDE, // rook
+        Square::E1 => CastlingRights::WHITE_KINGSIDE | CastlingRights::WHITE_QUEENSIDE, // king
+        Square::H1 => CastlingRights::WHITE_KINGSIDE, // rook
+        Square::A8 => CastlingRights::BLACK_QUEENSIDE, // rook
+        Square::E8 => CastlingRights::BLACK_KINGSIDE | CastlingRights::BLACK_QUEENSIDE, // king
+        Square::H8 => CastlingRights::BLACK_KINGSIDE, // rook
+        _  => CastlingRights::NONE,
+    }
+}
+
+/// State overwritten by a move that the move itself can't restore
+/// The key is kept as well so earlier positions can be compared for repetitions
+#[derive(Clone, Debug)]
+pub(crate) struct Undo {
+    mv: Move,
+    castling: CastlingRights,
+    en_passant: Option<Square>,
+    half_clock: u32,
+    pub(crate) hash: u64,
+    pawn_hash: u64,
+}
+
+impl GameState {
+    /// Play a move and update the rest of the state along with the bitboards
+    /// Turn, castling rights, en passant and both clocks are kept in sync
+    /// The move is remembered so it can be taken back with `unmake_move`
+    pub fn make_move(&mut self, mv: Move) {
+        self.history.push(Undo {
+            mv,
+            castling: self.castling,
+            en_passant: self.en_passant,
+            half_clock: self.half_clock,
+            hash: self.hash,
+            pawn_hash: self.pawn_hash,
+        });
+        self.apply_move(mv);
+    }
+
+    /// Take back the last move played, returning it
+    pub fn unmake_move(&mut self) -> Option<Move> {
+        let undo = self.history.pop()?;
+        let mv = undo.mv;
+
+        if mv.is_castle() {
+            let (rook_from, rook_to) = castling_rook_squares(mv.from, mv.to);
+            self.move_piece(rook_to, rook_from);
+        }
+
+        if promoted_piece(&mv).is_some() {
+            self.board.remove_piece(mv.to);
+            self.board.put_piece(mv.to, mv.piece);
+        }
+
+        self.move_piece(mv.to, mv.from);
+
+        if let Some(captured) = mv.captured {
+            self.board.put_piece(captured_square(&mv), captured);
+        }
+
+        self.castling = undo.castling;
+        self.en_passant = undo.en_passant;
+        self.half_clock = undo.half_clock;
+        self.hash = undo.hash;
+        self.pawn_hash = undo.pawn_hash;
+        self.side_to_move = !self.side_to_move;
+        if !self.side_to_move.is_white() {
+            self.move_count -= 1;
+        }
+
+        Some(mv)
+    }
+
+    /// Moves played since the position was set up, oldest first
+    pub fn move_history(&self) -> Vec<Move> {
+        self.history.iter().map(|undo| undo.mv).collect()
+    }
+
+    /// Update the board and state without recording the move
+    pub(crate) fn apply_move(&mut self, mv: Move) {
+        self.hash ^= castling_key(self.castling) ^ en_passant_key(self.en_passant) ^ side_key();
+        self.toggle_piece_key(mv.piece, mv.from);
+
+        if let Some(captured) = mv.captured {
+            self.toggle_piece_key(captured, captured_square(&mv));
+        }
+
+        self.move_piece(mv.from, mv.to);
+
+        // Capture the pawn that skipped over the en passant square
+        if mv.is_en_passant() {
+            self.board.remove_piece(captured_square(&mv));
+        }
+
+        let new_piece = promoted_piece(&mv);
+        if let Some(new_piece) = new_piece {
+            self.board.remove_piece(mv.to);
+            self.board.put_piece(mv.to, new_piece);
+        }
+        self.toggle_piece_key(new_piece.unwrap_or(mv.piece), mv.to);
+
+        // Bring the rook along when castling
+        if mv.is_castle() {
+            let (rook_from, rook_to) = castling_rook_squares(mv.from, mv.to);
+            let rook = Piece::new(mv.piece.color, PieceKind::Rook);
+            self.move_piece(rook_from, rook_to);
+            self.toggle_piece_key(rook, rook_from);
+            self.toggle_piece_key(rook, rook_to);
+        }
+
+        self.castling
+            .remove(castling_rights_lost(mv.from) | castling_rights_lost(mv.to));
+
+        // Only kept when an enemy pawn could take, so positions reached by a double
+        // push and by two single pushes share a key
+        let enemy_pawns = self
+            .board
+            .pieces(Piece::new(!mv.piece.color, PieceKind::Pawn));
+        self.en_passant = mv
+            .is_double_push()
+            .then(|| {
+                Square::from_index((mv.from.index() + mv.to.index()) / 2) // the square skipped over
+            })
+            .filter(|&skipped| {
+                !(attacks::pawn_attacks(skipped, mv.piece.color) & enemy_pawns).is_empty()
+            });
+        self.hash ^= castling_key(self.castling) ^ en_passant_key(self.en_passant);
+
+        if mv.piece.kind == PieceKind::Pawn || mv.is_capture() {
+            self.half_clock = 0;
+        } else {
+            self.half_clock += 1;
+        }
+
+        if !self.side_to_move.is_white() {
+            self.move_count += 1;
+        }
+        self.side_to_move = !self.side_to_move;
+    }
+}
+
+/// The piece a pawn turns into, coloured to match the pawn
+fn promoted_piece(mv: &Move) -> Option<Piece> {
+    let promote_to = mv
+        .promotion
+        .filter(|p| mv.piece.kind == PieceKind::Pawn && PROMOTIONS.contains(p))?;
+    Some(Piece::new(mv.piece.color, promote_to))
+}
+
+/// Where the captured piece stood, behind `to` for en passant
+fn captured_square(mv: &Move) -> Square {
+    match (mv.is_en_passant(), mv.is_white()) {
+        (true, true) => Square::from_index(mv.to.index() - 8),
+        (true, false) => Square::from_index(mv.to.index() + 8),
+        (false, _) => mv.to,
+    }
+}
+
+/// Where the rook starts and ends when the king castles from `from` to `to`
+fn castling_rook_squares(from: Square, to: Square) -> (Square, Square) {
+    let from = from.index();
+    let (rook_from, rook_to) = if to.index() > from {
+        (from + 3, from + 1) // king side
+    } else {
+        (from - 4, from - 1) // queen side
+    };
+    (Square::from_index(rook_from), Square::from_index(rook_to))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Color;
+
+    fn play(gs: &mut GameState, from: Square, to: Square, promotion: Option<PieceKind>) {
+        let mv = Move::new(gs, from, to, promotion).expect("Piece to move");
+        gs.make_move(mv);
+    }
+
+    #[test]
+    fn test_make_move() {
+        let mut gs = GameState::new();
+
+        play(&mut gs, Square::E2, Square::E4, None);
+        assert_eq!(
+            gs.to_string(),
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1"
+        );
+
+        play(&mut gs, Square::B8, Square::C6, None);
+        assert_eq!(
+            gs.to_string(),
+            "r1bqkbnr/pppppppp/2n5/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 1 2"
+        );
+
+        play(&mut gs, Square::G1, Square::F3, None);
+        assert_eq!(
+            gs.to_string(),
+            "r1bqkbnr/pppppppp/2n5/8/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 2 2"
+        );
+    }
+
+    #[test]
+    fn test_make_move_capture_resets_clock() {
+        let mut gs =
+            GameState::from_string("rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 4 2")
+                .unwrap();
+        play(&mut gs, Square::E4, Square::D5, None);
+        assert_eq!(gs.half_clock, 0);
+        assert_eq!(
+            gs.board.piece_at_position(Square::D5),
+            Piece::from_char('P')
+        );
+        assert_eq!(gs.side_to_move, Color::Black);
+    }
+
+    #[test]
+    fn test_make_move_castling_rights() {
+        let mut gs = GameState::from_string("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+
+        play(&mut gs, Square::H1, Square::G1, None); // rook moves
+        assert_eq!(gs.castling.to_string(), "Qkq");
+
+        play(&mut gs, Square::E8, Square::D8, None); // black king moves
+        assert_eq!(gs.castling.to_string(), "Q");
+
+        play(&mut gs, Square::A1, Square::A8, None); // rook captures the A8 rook
+        assert_eq!(gs.castling, CastlingRights::NONE);
+    }
+
+    #[test]
+    fn test_castling_moves_rook() {
+        let fen = "r3k2r/pppppppp/8/8/8/8/PPPPPPPP/R3K2R w KQkq - 0 1";
+
+        let mut gs = GameState::from_string(fen).unwrap();
+        play(&mut gs, Square::E1, Square::G1, None); // white king side
+        assert_eq!(
+            gs.to_string(),
+            "r3k2r/pppppppp/8/8/8/8/PPPPPPPP/R4RK1 b kq - 1 1"
+        );
+        play(&mut gs, Square::E8, Square::C8, None); // black queen side
+        assert_eq!(
+            gs.to_string(),
+            "2kr3r/pppppppp/8/8/8/8/PPPPPPPP/R4RK1 w - - 2 2"
+        );
+
+        let mut gs = GameState::from_string(fen).unwrap();
+        play(&mut gs, Square::E1, Square::C1, None); // white queen side
+        assert_eq!(
+            gs.to_string(),
+            "r3k2r/pppppppp/8/8/8/8/PPPPPPPP/2KR3R b kq - 1 1"
+        );
+        play(&mut gs, Square::E8, Square::G8, None); // black king side
+        assert_eq!(
+            gs.to_string(),
+            "r4rk1/pppppppp/8/8/8/8/PPPPPPPP/2KR3R w - - 2 2"
+        );
+    }
+
+    #[test]
+    fn test_en_passant() {
+        let mut gs =
+            GameState::from_string("rnbqkbnr/ppppp1pp/8/4Pp2/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 3")
+                .unwrap();
+
+        play(&mut gs, Square::D7, Square::D5, None);
+        assert_eq!(
+            gs.en_passant,
+            Some(Square::D6),
+            "Double push did not set en passant"
+        );
+
+        play(&mut gs, Square::E5, Square::D6, None); // en passant
+        assert_eq!(
+            gs.to_string(),
+            "rnbqkbnr/ppp1p1pp/3P4/5p2/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 4"
+        );
+
+        let mut gs =
+            GameState::from_string("rnbqkbnr/pppp1ppp/8/8/3Pp3/8/PPP1PPPP/RNBQKBNR b KQkq d3 0 2")
+                .unwrap();
+        play(&mut gs, Square::E4, Square::D3, None); // en passant
+        assert_eq!(
+            gs.board.piece_at_position(Square::D4),
+            None,
+            "Pawn not captured"
+        );
+        assert_eq!(
+            gs.board.piece_at_position(Square::D3),
+            Piece::from_char('p')
+        );
+    }
+
+    #[test]
+    fn test_en_passant_only_when_capturable() {
+        let mut gs = GameState::from_string("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1").unwrap();
+        play(&mut gs, Square::E2, Square::E4, None);
+        assert_eq!(gs.en_passant, None, "No black pawn can take on e3");
+
+        let direct = GameState::from_string("4k3/8/8/8/4P3/8/8/4K3 b - - 0 1").unwrap();
+        assert_eq!(gs.hash, direct.hash);
+    }
+
+    #[test]
+    fn test_promotion() {
+        let mut gs = GameState::from_string("1r5k/P7/8/8/8/8/7p/K7 w - - 3 40").unwrap();
+
+        play(&mut gs, Square::A7, Square::A8, Some(PieceKind::Queen));
+        assert_eq!(gs.to_string(), "Qr5k/8/8/8/8/8/7p/K7 b - - 0 40");
+
+        play(&mut gs, Square::H2, Square::H1, Some(PieceKind::Knight));
+        assert_eq!(gs.to_string(), "Qr5k/8/8/8/8/8/8/K6n w - - 0 41");
+
+        play(&mut gs, Square::A8, Square::B8, Some(PieceKind::Queen)); // not a pawn, promotion ignored
+        assert_eq!(
+            gs.board.piece_at_position(Square::B8),
+            Piece::from_char('Q')
+        );
+    }
+
+    #[test]
+    fn test_unmake_move() {
+        let fen = "r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 5 20";
+        let mut gs = GameState::from_string(fen).unwrap();
+
+        for (from, to, promotion) in [
+            (Square::E5, Square::D6, None),                    // en passant
+            (Square::E1, Square::G1, None),                    // castle
+            (Square::B7, Square::A8, Some(PieceKind::Knight)), // promotion capture
+            (Square::A1, Square::A2, None),                    // quiet rook move
+        ] {
+            play(&mut gs, from, to, promotion);
+            assert_ne!(gs.to_string(), fen);
+            let mv = gs.unmake_move().expect("A move was played");
+            assert_eq!((mv.from, mv.to), (from, to));
+            assert_eq!(gs.to_string(), fen, "{mv} was not undone");
+        }
+        assert!(gs.unmake_move().is_none(), "Nothing left to undo");
+    }
+
+    #[test]
+    fn test_move_history() {
+        let mut gs = GameState::new();
+        play(&mut gs, Square::E2, Square::E4, None);
+        play(&mut gs, Square::E7, Square::E5, None);
+        play(&mut gs, Square::G1, Square::F3, None);
+
+        let history: Vec<String> = gs.move_history().iter().map(|m| m.to_string()).collect();
+        assert_eq!(history, vec!["e2e4", "e7e5", "g1f3"]);
+
+        while gs.unmake_move().is_some() {}
+        assert_eq!(gs.to_string(), GameState::new().to_string());
+        assert!(gs.move_history().is_empty());
+    }
+
+    #[test]
+    fn test_unmake_every_legal_move() {
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        let mut gs = GameState::from_string(fen).unwrap();
+        for mv in gs.legal_moves() {
+            gs.make_move(mv);
+            gs.unmake_move();
+            assert_eq!(gs.to_string(), fen, "{mv} was not undone");
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use crate::attacks::all_pawn_attacks;
+use crate::pawns::Weight;
+use crate::{Bitboard, Color, GameState, Piece, PieceKind, Square};
+
+// https://www.chessprogramming.org/Mobility
+
+/// The pieces whose mobility is scored, the order the tables below are indexed in
+pub(crate) const MOBILE_PIECES: [PieceKind; 4] = [
+    PieceKind::Knight,
+    PieceKind::Bishop,
+    PieceKind::Rook,
+    PieceKind::Queen,
+];
+/// Bonus per reachable square above (or penalty below) `MOBILITY_BASELINE`
+const MOBILITY_WEIGHT: [Weight; 4] = [(4, 4), (5, 5), (2, 4), (1, 2)];
+/// Roughly how many squares each piece reaches in a typical position
+const MOBILITY_BASELINE: [i64; 4] = [4, 7, 7, 14];
+
+impl GameState {
+    /// Squares this piece could move to, or none for pawns and kings
+    /// Ignores checks and pins, which only matter for the move actually played
+    pub(crate) fn piece_reach(&self, square: Square, piece: Piece) -> Bitboard {
+        match piece.kind {
+            PieceKind::Knight => self.possible_knight_moves(square, piece.color),
+            PieceKind::Bishop => self.possible_bishop_moves(square, piece.color),
+            PieceKind::Rook => self.possible_rook_moves(square, piece.color),
+            PieceKind::Queen => self.possible_queen_moves(square, piece.color),
+            PieceKind::Pawn | PieceKind::King => Bitboard::EMPTY,
+        }
+    }
+
+    /// Middlegame and endgame score for how freely the pieces move, positive when white's
+    /// are more active. Squares covered by enemy pawns don't count
+    pub fn mobility(&self) -> Weight {
+        let (mut mg, mut eg) = (0, 0);
+
+        for (index, kind) in MOBILE_PIECES.into_iter().enumerate() {
+            let (weight_mg, weight_eg) = MOBILITY_WEIGHT[index];
+
+            for color in [Color::White, Color::Black] {
+                let piece = Piece::new(color, kind);
+                let sign = color.sign();
+                let enemy_pawns = self.board.pieces(Piece::new(!color, PieceKind::Pawn));
+                let unsafe_squares = all_pawn_attacks(enemy_pawns, !color);
+
+                for square in self.board.pieces(piece) {
+                    let reach = self.piece_reach(square, piece) & !unsafe_squares;
+                    let extra = reach.count() as i64 - MOBILITY_BASELINE[index];
+                    mg += sign * extra * weight_mg;
+                    eg += sign * extra * weight_eg;
+                }
+            }
+        }
+
+        (mg, eg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mobility(fen: &str) -> Weight {
+        GameState::from_string(fen).unwrap().mobility()
+    }
+
+    #[test]
+    fn test_mobility() {
+        assert_eq!(
+            mobility("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"),
+            (0, 0)
+        );
+
+        // A centralised knight beats one stuck in the corner
+        let (centre, _) = mobility("4k3/8/8/8/3N4/8/8/4K3 w - - 0 1");
+        let (corner, _) = mobility("4k3/8/8/8/8/8/8/N3K3 w - - 0 1");
+        assert_eq!(centre, (8 - 4) * 4);
+        assert_eq!(corner, (2 - 4) * 4);
+
+        // Squares guarded by pawns aren't really available
+        let (guarded, _) = mobility("4k3/8/2p1p3/8/3N4/8/8/4K3 w - - 0 1");
+        assert!(guarded < centre);
+    }
+
+    #[test]
+    fn test_mobility_symmetric() {
+        let (white_mg, white_eg) = mobility("4k3/8/8/8/3R4/8/8/4K3 w - - 0 1");
+        let (black_mg, black_eg) = mobility("4k3/8/8/3r4/8/8/8/4K3 w - - 0 1");
+        assert_eq!((white_mg, white_eg), (-black_mg, -black_eg));
+        assert!(white_mg > 0);
+    }
+}
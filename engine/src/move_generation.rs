@@ -1,421 +1,345 @@
-use crate::position;
+use crate::attacks;
+use crate::make_move::PROMOTIONS;
+use crate::{Bitboard, CastlingRights, Color, Error, IllegalMove, Move, Piece, PieceKind, Square};
 
 use super::GameState;
-use std::cmp::{max, min};
+
+/// Where a double pawn push lands
+const RANK_4: Bitboard = Bitboard::rank(3);
+const RANK_5: Bitboard = Bitboard::rank(4);
+
+/// Do both squares share a rank, file or diagonal
+fn aligned(a: Square, b: Square) -> bool {
+    let (a_rank, a_file) = (a.rank(), a.file());
+    let (b_rank, b_file) = (b.rank(), b.file());
+    a_rank == b_rank
+        || a_file == b_file
+        || a_rank + b_file == b_rank + a_file
+        || a_rank + a_file == b_rank + b_file
+}
 
 impl GameState {
-    pub fn move_piece(&mut self, from: u8, to: u8) {
-        if let Some(piece) = self.board.piece_at_position(to) {
-            let to_piece_bitboard = self
-                .board
-                .piece_bitboard(piece)
-                .expect("we already validate this");
-            *to_piece_bitboard &= !(1 << to);
-        }
+    pub fn move_piece(&mut self, from: Square, to: Square) {
+        self.board.remove_piece(to);
         if let Some(piece) = self.board.piece_at_position(from) {
-            let from_piece_bitboard = self
-                .board
-                .piece_bitboard(piece)
-                .expect("we already validate this");
-            *from_piece_bitboard &= !(1 << from);
-            *from_piece_bitboard |= 1 << to;
+            self.board.remove_piece(from);
+            self.board.put_piece(to, piece);
         }
     }
 
-    pub fn move_piece_legally(&mut self, from: u8, to: u8) -> Result<(), String> {
-        let possible_moves = self.possible_moves(from);
-        if 1 << to & possible_moves == 0 {
-            let from_string = position::square_to_string(from);
-            let to_string = &position::square_to_string(to);
-            return Err(format!("{from_string} -> {to_string} illegal move"));
+    pub fn move_piece_legally(
+        &mut self,
+        from: Square,
+        to: Square,
+        promotion: Option<PieceKind>,
+    ) -> Result<(), Error> {
+        let illegal = |reason| Err(Error::IllegalMove { from, to, reason });
+
+        match self.board.piece_at_position(from) {
+            None => return illegal(IllegalMove::NoPiece),
+            Some(p) if p.color != self.side_to_move => return illegal(IllegalMove::OutOfTurn),
+            _ => (),
         }
-        self.move_piece(from, to);
-        Ok(())
-    }
 
-    /// Move squares in iterator until a piece is hit
-    fn move_until_piece<I>(&self, range: I, white: bool) -> u64
-    where
-        I: Iterator<Item = u8>,
-    {
-        let mut result = 0;
-        let own = self.board.one_side_pieces(white);
-        let opps = self.board.one_side_pieces(!white);
-
-        for square in range {
-            let btwise = 1 << square;
-            if own & btwise != 0 {
-                break;
-            }
+        if !self.possible_moves(from).contains(to) {
+            return illegal(IllegalMove::NotReachable);
+        }
 
-            result |= btwise;
-            if opps & btwise != 0 {
-                break;
+        match (self.is_promotion(from, to), promotion) {
+            (true, None) => return illegal(IllegalMove::MissingPromotion),
+            (true, Some(p)) if !PROMOTIONS.contains(&p) => {
+                return illegal(IllegalMove::InvalidPromotion(p));
             }
+            (false, Some(_)) => return illegal(IllegalMove::NotPromotion),
+            _ => (),
         }
 
-        result
+        let mv = Move::new(self, from, to, promotion).expect("Piece checked above");
+        self.make_move(mv);
+        Ok(())
     }
 
-    pub fn possible_moves(&self, square: u8) -> u64 {
-        let piece = match self.board.piece_at_position(square) {
-            Some(p) => p,
-            None => return 0,
-        };
-
-        let is_white = piece.is_ascii_uppercase();
-        match piece.to_ascii_lowercase() {
-            'p' => self.possible_pawn_moves(square, is_white),
-            'r' => self.possible_rook_moves(square, is_white),
-            'n' => self.possible_knight_moves(square, is_white),
-            'b' => self.possible_bishop_moves(square, is_white),
-            'k' => self.possible_king_moves(square, is_white),
-            'q' => self.possible_queen_moves(square, is_white),
-            _ => 0,
+    /// Is this a pawn moving onto the last rank
+    fn is_promotion(&self, from: Square, to: Square) -> bool {
+        match self.board.piece_at_position(from) {
+            Some(p) if p.kind == PieceKind::Pawn => match p.color {
+                Color::White => to.rank() == 7,
+                Color::Black => to.rank() == 0,
+            },
+            _ => false,
         }
     }
 
-    fn possible_pawn_moves(&self, from: u8, white: bool) -> u64 {
-        let mut result = 0;
-        let rank = from / 8;
-        let direction = if white { 1 } else { -1 };
-        let initial_rank = if white { rank == 1 } else { rank == 6 };
-
-        let out_of_bounds = (direction == 1 && from > 55) || (direction == -1 && from < 8);
-        if out_of_bounds {
-            return result;
-        }
-
-        let opps = self.board.one_side_pieces(!white);
-        let taken = self.board.both_side_pieces();
-
-        let left_diag = (from as i32 + 7 * direction) as u8;
-        let forward = from as i32 + 8 * direction;
-        let right_diag = (from as i32 + 9 * direction) as u8;
+    /// Every legal move the piece on this square can make
+    /// A pawn reaching the last rank produces one move per promotion piece
+    pub fn possible_move_list(&self, square: Square) -> Vec<Move> {
+        self.move_list(square, Bitboard::FULL)
+    }
 
-        if taken & (1 << forward) == 0 {
-            result |= 1 << forward;
-            if initial_rank {
-                let double = forward + 8 * direction;
-                if taken & (1 << double) == 0 {
-                    result |= 1 << double;
-                }
+    /// Legal moves from this square landing on one of the `targets` squares
+    fn move_list(&self, square: Square, targets: Bitboard) -> Vec<Move> {
+        let mut result = Vec::new();
+        for to in self.legal_destinations(square, targets) {
+            if self.is_promotion(square, to) {
+                result.extend(
+                    PROMOTIONS
+                        .iter()
+                        .filter_map(|&p| Move::new(self, square, to, Some(p))),
+                );
+            } else {
+                result.extend(Move::new(self, square, to, None));
             }
         }
-
-        let opp_left = opps & (1 << left_diag) != 0;
-        let en_passant_left = !initial_rank && left_diag == self.en_passant;
-        if opp_left || en_passant_left {
-            result |= 1 << left_diag;
-        }
-
-        let opp_right = opps & (1 << right_diag) != 0;
-        let en_passant_right = !initial_rank && right_diag == self.en_passant;
-        if opp_right || en_passant_right {
-            result |= 1 << right_diag;
-        }
-
         result
     }
 
-    fn possible_rook_moves(&self, from: u8, white: bool) -> u64 {
-        let mut result = 0;
-        let file = from % 8;
-        let left_bound = from - file;
-        let right_bound = left_bound + 7;
+    /// Every legal move for the side whose turn it is
+    pub fn legal_moves(&self) -> Vec<Move> {
+        self.board
+            .one_side_pieces(self.side_to_move)
+            .into_iter()
+            .flat_map(|square| self.possible_move_list(square))
+            .collect()
+    }
 
-        result |= self.move_until_piece((left_bound..from).rev(), white); // west moves
-        result |= self.move_until_piece(from + 1..=right_bound, white); // east moves
-        result |= self.move_until_piece((from + 8..=63).step_by(8), white); // north moves
-        result |= self.move_until_piece((file..from).step_by(8).rev(), white); // south moves
+    /// Legal captures (en passant included) and promotions for the side whose turn it is
+    /// The moves that can change the material balance, used by the quiescence search
+    pub fn capture_moves(&self) -> Vec<Move> {
+        let opponents = self.board.one_side_pieces(!self.side_to_move);
+        let mut targets = opponents;
+        if let Some(square) = self.en_passant {
+            targets |= square.bitboard();
+        }
+        let (pawns, last_rank) = match self.side_to_move {
+            Color::White => (self.board.white_pawns, Bitboard::RANK_8),
+            Color::Black => (self.board.black_pawns, Bitboard::RANK_1),
+        };
 
-        result
+        self.board
+            .one_side_pieces(self.side_to_move)
+            .into_iter()
+            .flat_map(|square| {
+                if pawns.contains(square) {
+                    self.move_list(square, targets | last_rank)
+                } else {
+                    // Only pawns can use the en passant square
+                    self.move_list(square, opponents)
+                }
+            })
+            .collect()
     }
 
-    fn rook_attack_map(&self, pos: u8, white: bool) -> u64 {
-        let rank = pos / 8;
-        let mut result = 0;
-        let possible_attacks =
-            self.possible_rook_moves(pos, white) & self.board.one_side_pieces(!white);
-
-        // Find the furthest move in each direction (north, east, south, west)
-        let north = !((1 << (pos + 1)) - 1); // everything above `pos` is 1
-        let south = (1 << pos) - 1; // everything below `pos` is 1
-        let horz = 0xFF << (rank * 8); // everything on the same rank as `pos`
-        let east = horz & north;
-        let west = horz & south;
+    /// Squares the piece on this square can legally move to
+    /// Moves that leave its own king under attack (pins, unanswered checks) are removed
+    pub fn possible_moves(&self, square: Square) -> Bitboard {
+        self.legal_destinations(square, Bitboard::FULL)
+    }
 
-        let north_most = (possible_attacks & north).leading_zeros();
-        if north_most < 64 {
-            result |= 1 << (63 - north_most);
-        }
+    /// `possible_moves` limited to `targets`, which skips checking moves that aren't wanted
+    fn legal_destinations(&self, square: Square, targets: Bitboard) -> Bitboard {
+        let moves = self.possible_moves_ignore_check(square) & targets;
+        let piece = match self.board.piece_at_position(square) {
+            Some(p) => p,
+            None => return Bitboard::EMPTY,
+        };
+        let king = match self.king_square(piece.color) {
+            Some(k) => k,
+            None => return moves, // nothing to protect
+        };
 
-        let south_most = (possible_attacks & south).trailing_zeros();
-        if south_most < 64 {
-            result |= 1 << south_most;
+        // A piece off every line through its king can't be pinned, so unless
+        // the king is in check (or en passant exposes it) every move is fine
+        let is_king = square == king;
+        let is_en_passant =
+            piece.kind == PieceKind::Pawn && self.en_passant.is_some_and(|ep| moves.contains(ep));
+        if !is_king
+            && !is_en_passant
+            && !aligned(square, king)
+            && !self.position_under_attack(king, piece.color)
+        {
+            return moves;
         }
 
-        let east_most = (possible_attacks & east).leading_zeros();
-        if east_most < 64 {
-            result |= 1 << (63 - east_most);
-        }
+        moves
+            .into_iter()
+            .filter(|&to| !self.leaves_king_in_check(square, to))
+            .collect()
+    }
 
-        let west_most = (possible_attacks & west).trailing_zeros();
-        if west_most < 64 {
-            result |= 1 << west_most;
+    pub(crate) fn king_square(&self, color: Color) -> Option<Square> {
+        match color {
+            Color::White => self.board.white_king.first(),
+            Color::Black => self.board.black_king.first(),
         }
-
-        result
     }
 
-    fn possible_bishop_moves(&self, from: u8, white: bool) -> u64 {
-        let mut result = 0;
-
-        let file = from % 8; // how many rows we can move right
-        let nw_bound = min(56, from + file * 7);
-        let sw_bound = from.saturating_sub(file * 9);
-
-        let inv_file = 7 - file; // inverse rank (how many rows we can move left)
-        let ne_bound = min(63, from + inv_file * 9);
-        let se_bound = max(0, from.saturating_sub(inv_file * 7));
-
-        let nw = (from + 7..=nw_bound).step_by(7);
-        let sw = (sw_bound..=from.saturating_sub(9)).rev().step_by(9);
-        let ne = (from + 9..=ne_bound).step_by(9);
-        let se = (se_bound..=from.saturating_sub(7)).rev().step_by(7);
-
-        result |= self.move_until_piece(nw, white);
-        result |= self.move_until_piece(sw, white);
-        result |= self.move_until_piece(ne, white);
-        result |= self.move_until_piece(se, white);
+    /// Play the move on a copy and see if the mover's king can be taken
+    fn leaves_king_in_check(&self, from: Square, to: Square) -> bool {
+        let color = match self.board.piece_at_position(from) {
+            Some(p) => p.color,
+            None => return false,
+        };
 
-        result
+        // Copy everything but the history, which the check doesn't need
+        let mut after = GameState {
+            board: self.board.clone(),
+            history: Vec::new(),
+            ..*self
+        };
+        after.apply_move(Move::new(self, from, to, None).expect("Piece checked above"));
+        match after.king_square(color) {
+            Some(king) => after.position_under_attack(king, color),
+            None => false,
+        }
     }
 
-    fn bishop_attack_map(&self, pos: u8, white: bool) -> u64 {
-        let mut result = 0;
-        let file = pos % 8;
-        let possible_attacks =
-            self.possible_bishop_moves(pos, white) & self.board.one_side_pieces(!white);
-
-        let west_of_file = (1 << file) - 1_u64;
-        let east_of_file = 0xFF & !((1 << (file + 1)) - 1_u64);
-
-        let west = possible_attacks
-            & (west_of_file
-                | west_of_file << 8
-                | west_of_file << (8 * 2)
-                | west_of_file << (8 * 3)
-                | west_of_file << (8 * 4)
-                | west_of_file << (8 * 5)
-                | west_of_file << (8 * 6)
-                | west_of_file << (8 * 7));
-
-        let east = possible_attacks
-            & (east_of_file
-                | east_of_file << 8
-                | east_of_file << (8 * 2)
-                | east_of_file << (8 * 3)
-                | east_of_file << (8 * 4)
-                | east_of_file << (8 * 5)
-                | east_of_file << (8 * 6)
-                | east_of_file << (8 * 7));
-
-        let ne_most = east.leading_zeros();
-        if ne_most < 64 {
-            result |= 1 << (63 - ne_most);
-        }
+    fn possible_moves_ignore_check(&self, square: Square) -> Bitboard {
+        let piece = match self.board.piece_at_position(square) {
+            Some(p) => p,
+            None => return Bitboard::EMPTY,
+        };
 
-        let nw_most = west.leading_zeros();
-        if nw_most < 64 {
-            result |= 1 << (63 - nw_most);
+        match piece.kind {
+            PieceKind::Pawn => self.possible_pawn_moves(square, piece.color),
+            PieceKind::Rook => self.possible_rook_moves(square, piece.color),
+            PieceKind::Knight => self.possible_knight_moves(square, piece.color),
+            PieceKind::Bishop => self.possible_bishop_moves(square, piece.color),
+            PieceKind::King => self.possible_king_moves(square, piece.color),
+            PieceKind::Queen => self.possible_queen_moves(square, piece.color),
         }
+    }
 
-        let se_most = east.trailing_zeros();
-        if se_most < 64 {
-            result |= 1 << se_most;
-        }
+    fn possible_pawn_moves(&self, from: Square, color: Color) -> Bitboard {
+        let pawn = from.bitboard();
+        let empty = !self.board.both_side_pieces();
+        let opps = self.board.one_side_pieces(!color);
+        let initial_rank = match color {
+            Color::White => from.rank() == 1,
+            Color::Black => from.rank() == 6,
+        };
 
-        let sw_most = west.trailing_zeros();
-        if sw_most < 64 {
-            result |= 1 << sw_most;
+        // Shifting a whole rank never wraps, a pawn on the last rank just falls off the board
+        let (single, double) = match color {
+            Color::White => {
+                let single = pawn.north() & empty;
+                (single, single.north() & empty & RANK_4)
+            }
+            Color::Black => {
+                let single = pawn.south() & empty;
+                (single, single.south() & empty & RANK_5)
+            }
+        };
+        let mut result = single | double;
+
+        let diagonals = attacks::pawn_attacks(from, color);
+        result |= diagonals & opps;
+        if let Some(en_passant) = self.en_passant.filter(|_| !initial_rank) {
+            result |= diagonals & en_passant.bitboard();
         }
 
         result
     }
 
-    fn possible_queen_moves(&self, from: u8, white: bool) -> u64 {
-        self.possible_rook_moves(from, white) | self.possible_bishop_moves(from, white)
+    pub(crate) fn possible_rook_moves(&self, from: Square, color: Color) -> Bitboard {
+        attacks::rook_attacks(from, self.board.both_side_pieces())
+            & !self.board.one_side_pieces(color)
     }
 
-    fn possible_king_moves_ignore_check(&self, from: u8, white: bool) -> u64 {
-        let mut result = 0;
-        let directions: [i8; 8] = [-1, 1, -7, 7, -8, 8, -9, 9];
-        let own = self.board.one_side_pieces(white);
-
-        for &direction in &directions {
-            let target = from as i8 + direction;
-            if (0..=63).contains(&target) && own & (1 << target) == 0 {
-                result |= 1 << target;
-            }
-        }
+    fn rook_attack_map(&self, pos: Square, color: Color) -> Bitboard {
+        attacks::rook_attacks(pos, self.board.both_side_pieces())
+            & self.board.one_side_pieces(!color)
+    }
 
-        result
+    pub(crate) fn possible_bishop_moves(&self, from: Square, color: Color) -> Bitboard {
+        attacks::bishop_attacks(from, self.board.both_side_pieces())
+            & !self.board.one_side_pieces(color)
     }
 
-    fn possible_king_moves(&self, from: u8, white: bool) -> u64 {
-        let mut all_moves = self.possible_king_moves_ignore_check(from, white);
-        let mut filtered = 0;
+    fn bishop_attack_map(&self, pos: Square, color: Color) -> Bitboard {
+        attacks::bishop_attacks(pos, self.board.both_side_pieces())
+            & self.board.one_side_pieces(!color)
+    }
 
-        // Filter out squares under attack
-        while all_moves != 0 {
-            let to = all_moves.trailing_zeros() as u8;
-            let mask = 1 << to;
-            if !self.position_under_attack(to, white) {
-                filtered |= mask;
-            }
-            all_moves &= all_moves - 1;
-        }
+    pub(crate) fn possible_queen_moves(&self, from: Square, color: Color) -> Bitboard {
+        attacks::queen_attacks(from, self.board.both_side_pieces())
+            & !self.board.one_side_pieces(color)
+    }
 
-        // check for castling possibility
-        let capital_k = self.castling & 0b1000 != 0;
-        let capital_q = self.castling & 0b0100 != 0;
-        let k = self.castling & 0b0010 != 0;
-        let q = self.castling & 0b0001 != 0;
+    fn possible_king_moves_ignore_check(&self, from: Square, color: Color) -> Bitboard {
+        attacks::king_attacks(from) & !self.board.one_side_pieces(color)
+    }
 
-        // check which side we can castle too
-        let king_side = capital_k & white || k & !white;
-        let queen_side = capital_q & white || q & !white;
-        if (!king_side & !queen_side) || self.position_under_attack(from, white) {
+    fn possible_king_moves(&self, from: Square, color: Color) -> Bitboard {
+        // Filter out squares under attack
+        let mut filtered: Bitboard = self
+            .possible_king_moves_ignore_check(from, color)
+            .into_iter()
+            .filter(|&to| !self.position_under_attack(to, color))
+            .collect();
+
+        // check which side we can castle too, the rights alone can't be trusted
+        // as a hand written FEN may keep them with the king or rooks elsewhere
+        let (home, king_rook, queen_rook) = match color {
+            Color::White => (Square::E1, Square::H1, Square::A1),
+            Color::Black => (Square::E8, Square::H8, Square::A8),
+        };
+        let has_rook = |square| {
+            self.board.piece_at_position(square) == Some(Piece::new(color, PieceKind::Rook))
+        };
+        let king_side = from == home
+            && has_rook(king_rook)
+            && self.castling.contains(CastlingRights::kingside(color));
+        let queen_side = from == home
+            && has_rook(queen_rook)
+            && self.castling.contains(CastlingRights::queenside(color));
+        if (!king_side & !queen_side) || self.position_under_attack(from, color) {
             return filtered;
         }
 
-        let shift = 8 * (from / 8);
-        let greater_than = !((1u64 << (from + 1)) - 1); // everything above `pos` is 1
-        let less_than = (1u64 << from) - 1; // everything below `pos` is 1
-        let horz = (0xFF << shift) & self.board.both_side_pieces(); // everything on the same rank as `pos`
-        let east = horz & greater_than;
-        let west = horz & less_than;
-
-        let blocked_k_side = !king_side || east & (0b01100000 << shift) != 0;
-        let blocked_q_side = !queen_side || west & (0b00001110 << shift) != 0;
+        let shift = 8 * from.rank() as u32;
+        let rank = Bitboard::rank(from.rank()) & self.board.both_side_pieces(); // everything on the same rank as `from`
+        let blocked_k_side = !king_side || !(rank & Bitboard(0b01100000) << shift).is_empty();
+        let blocked_q_side = !queen_side || !(rank & Bitboard(0b00001110) << shift).is_empty();
+        let step = |offset: i8| Square::from_index(from.index().wrapping_add_signed(offset));
         if !blocked_k_side
-            && !self.position_under_attack(from + 1, white)
-            && !self.position_under_attack(from + 2, white)
+            && !self.position_under_attack(step(1), color)
+            && !self.position_under_attack(step(2), color)
         {
-            filtered |= 0b01000000 << shift;
+            filtered |= Bitboard(0b01000000) << shift;
         }
         if !blocked_q_side
-            && !self.position_under_attack(from - 1, white)
-            && !self.position_under_attack(from - 2, white)
+            && !self.position_under_attack(step(-1), color)
+            && !self.position_under_attack(step(-2), color)
         {
-            filtered |= 0b00000100 << shift;
+            filtered |= Bitboard(0b00000100) << shift;
         }
 
         filtered //all moves the king can make
     }
 
-    fn king_attack_map_ignore_check(&self, pos: u8, white: bool) -> u64 {
-        self.possible_king_moves_ignore_check(pos, white) & self.board.one_side_pieces(!white)
-    }
-
-    fn possible_knight_moves(&self, from: u8, white: bool) -> u64 {
-        let file = from % 8;
-        let rank = from / 8;
-        let own = self.board.one_side_pieces(white);
-        let mut result = 0;
-
-        let moves: [(i8, i8); 8] = [
-            (-1, -2),
-            (-1, 2),
-            (1, -2),
-            (1, 2),
-            (-2, -1),
-            (-2, 1),
-            (2, -1),
-            (2, 1),
-        ];
-
-        for (d_rank, d_file) in moves.iter() {
-            let n_rank = d_rank + rank as i8;
-            let n_file = d_file + file as i8;
-            if !(0..8).contains(&n_rank) || !(0..8).contains(&n_file) {
-                continue;
-            }
-
-            let target = (n_rank * 8 + n_file) as u8;
-            if own & (1 << target) == 0 {
-                result |= 1 << target;
-            }
-        }
+    fn king_attack_map_ignore_check(&self, pos: Square, color: Color) -> Bitboard {
+        self.possible_king_moves_ignore_check(pos, color) & self.board.one_side_pieces(!color)
+    }
 
-        result
+    pub(crate) fn possible_knight_moves(&self, from: Square, color: Color) -> Bitboard {
+        attacks::knight_attacks(from) & !self.board.one_side_pieces(color)
     }
 
-    fn knight_attack_map(&self, pos: u8, white: bool) -> u64 {
-        self.possible_knight_moves(pos, white) & self.board.one_side_pieces(!white)
+    fn knight_attack_map(&self, pos: Square, color: Color) -> Bitboard {
+        self.possible_knight_moves(pos, color) & self.board.one_side_pieces(!color)
     }
 
     /// Can this square be taken by the opponent next turn?
-    fn position_under_attack(&self, square: u8, white: bool) -> bool {
-        let file = square % 8;
-        let opp_rooks = if white {
-            self.board.black_rooks | self.board.black_queen
-        } else {
-            self.board.white_rooks | self.board.white_queen
-        };
-        let opp_bish = if white {
-            self.board.black_bishops | self.board.black_queen
-        } else {
-            self.board.white_bishops | self.board.white_queen
-        };
-        let opp_knights = if white {
-            self.board.black_knights
-        } else {
-            self.board.white_knights
-        };
-        let opp_king = if white {
-            self.board.black_king
-        } else {
-            self.board.white_king
-        };
-        let opp_pawn = if white {
-            self.board.black_pawns
-        } else {
-            self.board.white_pawns
-        };
-
-        let pawn_attack_map = if white {
-            if square >= 48 {
-                0
-            } else {
-                let mut map = 0;
-                if file != 0 {
-                    map |= 1 << (square + 7);
-                }
-                if file != 7 {
-                    map |= 1 << (square + 9);
-                }
-                map
-            }
-        } else if square <= 15 {
-            0
-        } else {
-            let mut map = 0;
-            if file != 0 {
-                map |= 1 << (square - 9);
-            }
-            if file != 7 {
-                map |= 1 << (square - 7);
-            }
-            map
-        };
-
-        self.rook_attack_map(square, white) & opp_rooks != 0
-            || self.bishop_attack_map(square, white) & opp_bish != 0
-            || self.knight_attack_map(square, white) & opp_knights != 0
-            || self.king_attack_map_ignore_check(square, white) & opp_king != 0
-            || pawn_attack_map & opp_pawn != 0
+    pub(crate) fn position_under_attack(&self, square: Square, color: Color) -> bool {
+        let opp = |kind| self.board.pieces(Piece::new(!color, kind));
+        let opp_rooks = opp(PieceKind::Rook) | opp(PieceKind::Queen);
+        let opp_bish = opp(PieceKind::Bishop) | opp(PieceKind::Queen);
+
+        !(self.rook_attack_map(square, color) & opp_rooks).is_empty()
+            || !(self.bishop_attack_map(square, color) & opp_bish).is_empty()
+            || !(self.knight_attack_map(square, color) & opp(PieceKind::Knight)).is_empty()
+            || !(self.king_attack_map_ignore_check(square, color) & opp(PieceKind::King)).is_empty()
+            || !(attacks::pawn_attacks(square, color) & opp(PieceKind::Pawn)).is_empty()
     }
 }
 
@@ -429,65 +353,87 @@ mod tests {
     fn test_pawn_moves() {
         let mut gs = GameState::new();
         assert_eq!(
-            gs.possible_pawn_moves(17, true),
-            1 << 25,
+            gs.possible_pawn_moves(Square::B3, Color::White),
+            Square::B4.bitboard(),
             "Failed normal move"
         );
         assert_eq!(
-            gs.possible_pawn_moves(9, true),
-            1 << 17 | 1 << 25,
+            gs.possible_pawn_moves(Square::B2, Color::White),
+            Bitboard::from([Square::B3, Square::B4]),
             "Failed beginning move"
         );
         assert_eq!(
-            gs.possible_pawn_moves(49, false),
-            1 << 41 | 1 << 33,
+            gs.possible_pawn_moves(Square::B7, Color::Black),
+            Bitboard::from([Square::B6, Square::B5]),
             "Failed black start move"
         );
         assert_eq!(
-            gs.possible_pawn_moves(42, true),
-            1 << 49 | 1 << 51,
+            gs.possible_pawn_moves(Square::C6, Color::White),
+            Bitboard::from([Square::B7, Square::D7]),
             "Failed white->black capture"
         );
 
         // out of bounds
-        assert_eq!(gs.possible_pawn_moves(57, true), 0, "Failed white oob");
-        assert_eq!(gs.possible_pawn_moves(1, false), 0, "Failed black oob");
+        assert_eq!(
+            gs.possible_pawn_moves(Square::B8, Color::White),
+            Bitboard::EMPTY,
+            "Failed white oob"
+        );
+        assert_eq!(
+            gs.possible_pawn_moves(Square::B1, Color::Black),
+            Bitboard::EMPTY,
+            "Failed black oob"
+        );
 
-        gs.en_passant = 16;
+        gs.en_passant = Some(Square::A3);
         assert_eq!(
-            gs.possible_pawn_moves(9, true),
-            1 << 17 | 1 << 25,
+            gs.possible_pawn_moves(Square::B2, Color::White),
+            Bitboard::from([Square::B3, Square::B4]),
             "En-passanted own piece"
         );
-        gs.en_passant = 24;
+        gs.en_passant = Some(Square::A4);
         assert_eq!(
-            gs.possible_pawn_moves(17, true),
-            1 << 24 | 1 << 25,
+            gs.possible_pawn_moves(Square::B3, Color::White),
+            Bitboard::from([Square::A4, Square::B4]),
             "Did not en passant"
         );
 
-        assert_eq!(gs.possible_pawn_moves(1, true), 0, "Moved behind own piece");
-        gs.board.black_pawns |= 1 << 8; // place a black pawn on 8
-        assert_eq!(gs.possible_pawn_moves(1, true), 1 << 8, "Failed capture");
+        assert_eq!(
+            gs.possible_pawn_moves(Square::B1, Color::White),
+            Bitboard::EMPTY,
+            "Moved behind own piece"
+        );
+        gs.board.black_pawns |= Square::A2.bitboard(); // place a black pawn on A2
+        assert_eq!(
+            gs.possible_pawn_moves(Square::B1, Color::White),
+            Square::A2.bitboard(),
+            "Failed capture"
+        );
     }
 
     #[test]
     fn test_rook_moves() {
         let gs = GameState::new();
-        assert_eq!(gs.possible_rook_moves(0, true), 0, "Captured own");
-        assert_eq!(
-            gs.possible_rook_moves(33, true),
-            1 << 32
-                | 1 << 34
-                | 1 << 35
-                | 1 << 36
-                | 1 << 37
-                | 1 << 38
-                | 1 << 39
-                | 1 << 41
-                | 1 << 49
-                | 1 << 25
-                | 1 << 17,
+        assert_eq!(
+            gs.possible_rook_moves(Square::A1, Color::White),
+            Bitboard::EMPTY,
+            "Captured own"
+        );
+        assert_eq!(
+            gs.possible_rook_moves(Square::B5, Color::White),
+            Bitboard::from([
+                Square::A5,
+                Square::C5,
+                Square::D5,
+                Square::E5,
+                Square::F5,
+                Square::G5,
+                Square::H5,
+                Square::B6,
+                Square::B7,
+                Square::B4,
+                Square::B3
+            ]),
             "Failed normal move"
         );
     }
@@ -495,25 +441,41 @@ mod tests {
     #[test]
     fn test_rook_attacks() {
         let mut gs = GameState::new();
-        assert_eq!(gs.rook_attack_map(8, true), 1 << 48, "Did not attack north");
         assert_eq!(
-            gs.rook_attack_map(49, false),
-            1 << 9,
+            gs.rook_attack_map(Square::A2, Color::White),
+            Square::A7.bitboard(),
+            "Did not attack north"
+        );
+        assert_eq!(
+            gs.rook_attack_map(Square::B7, Color::Black),
+            Square::B2.bitboard(),
             "Did not attack south"
         );
 
         gs.board = Chessboard::empty();
-        assert_eq!(gs.rook_attack_map(0, true), 0, "Attacking nothing");
+        assert_eq!(
+            gs.rook_attack_map(Square::A1, Color::White),
+            Bitboard::EMPTY,
+            "Attacking nothing"
+        );
 
-        gs.board.black_pawns |= 1 << 1;
-        assert_eq!(gs.rook_attack_map(0, true), 1 << 1, "Did not attack east");
-        assert_eq!(gs.rook_attack_map(7, true), 1 << 1, "Did not attack west");
+        gs.board.black_pawns |= Square::B1.bitboard();
+        assert_eq!(
+            gs.rook_attack_map(Square::A1, Color::White),
+            Square::B1.bitboard(),
+            "Did not attack east"
+        );
+        assert_eq!(
+            gs.rook_attack_map(Square::H1, Color::White),
+            Square::B1.bitboard(),
+            "Did not attack west"
+        );
 
         // surround a rook with pieces
-        let pawns = 1 << 1 | 1 << 8 | 1 << 17 | 1 << 10;
+        let pawns = Bitboard::from([Square::B1, Square::A2, Square::B3, Square::C2]);
         gs.board.black_pawns = pawns;
         assert_eq!(
-            gs.rook_attack_map(9, true),
+            gs.rook_attack_map(Square::B2, Color::White),
             pawns,
             "Did not attack all directions"
         );
@@ -522,10 +484,23 @@ mod tests {
     #[test]
     fn test_bishop_moves() {
         let gs = GameState::new();
-        assert_eq!(gs.possible_bishop_moves(2, true), 0, "Captured own");
         assert_eq!(
-            gs.possible_bishop_moves(34, true),
-            1 << 41 | 1 << 48 | 1 << 25 | 1 << 16 | 1 << 43 | 1 << 52 | 1 << 27 | 1 << 20,
+            gs.possible_bishop_moves(Square::C1, Color::White),
+            Bitboard::EMPTY,
+            "Captured own"
+        );
+        assert_eq!(
+            gs.possible_bishop_moves(Square::C5, Color::White),
+            Bitboard::from([
+                Square::B6,
+                Square::A7,
+                Square::B4,
+                Square::A3,
+                Square::D6,
+                Square::E7,
+                Square::D4,
+                Square::E3
+            ]),
             "Failed normal move"
         );
     }
@@ -535,29 +510,36 @@ mod tests {
         let mut gs = GameState::new();
         gs.board = Chessboard::empty();
 
-        assert_eq!(gs.bishop_attack_map(0, true), 0, "Attacking nothing");
+        assert_eq!(
+            gs.bishop_attack_map(Square::A1, Color::White),
+            Bitboard::EMPTY,
+            "Attacking nothing"
+        );
 
-        gs.board.black_pawns |= 1 << 18;
-        assert_eq!(gs.bishop_attack_map(0, true), 1 << 18);
-        gs.board.black_pawns |= 1 << 9;
+        gs.board.black_pawns |= Square::C3.bitboard();
+        assert_eq!(
+            gs.bishop_attack_map(Square::A1, Color::White),
+            Square::C3.bitboard()
+        );
+        gs.board.black_pawns |= Square::B2.bitboard();
         assert_eq!(
-            gs.bishop_attack_map(0, true),
-            1 << 9,
+            gs.bishop_attack_map(Square::A1, Color::White),
+            Square::B2.bitboard(),
             "Attacked through piece"
         );
 
-        let pawns = 1 << 16 | 1 << 18 | 1 | 1 << 2;
+        let pawns = Bitboard::from([Square::A3, Square::C3, Square::A1, Square::C1]);
         gs.board.black_pawns = pawns;
         assert_eq!(
-            gs.bishop_attack_map(9, true),
+            gs.bishop_attack_map(Square::B2, Color::White),
             pawns,
             "Did not attack all directions"
         );
 
-        gs.board.black_pawns = 0;
+        gs.board.black_pawns = Bitboard::EMPTY;
         gs.board.white_pawns = pawns;
         assert_eq!(
-            gs.bishop_attack_map(9, false),
+            gs.bishop_attack_map(Square::B2, Color::Black),
             pawns,
             "Black did not attack all directions"
         );
@@ -566,114 +548,370 @@ mod tests {
     #[test]
     fn test_knight_moves() {
         let gs = GameState::new();
-        assert_eq!(gs.possible_knight_moves(1, true), 1 << 16 | 1 << 18); // white left starting
         assert_eq!(
-            gs.possible_knight_moves(1, false),
-            1 << 11 | 1 << 16 | 1 << 18
+            gs.possible_knight_moves(Square::B1, Color::White),
+            Bitboard::from([Square::A3, Square::C3])
+        ); // white left starting
+        assert_eq!(
+            gs.possible_knight_moves(Square::B1, Color::Black),
+            Bitboard::from([Square::D2, Square::A3, Square::C3])
         ); // black taking
-        assert_eq!(gs.possible_knight_moves(6, true), 1 << 21 | 1 << 23); // white right starting
         assert_eq!(
-            gs.possible_knight_moves(34, true),
-            1 << 24 | 1 << 28 | 1 << 40 | 1 << 44 | 1 << 17 | 1 << 19 | 1 << 49 | 1 << 51,
+            gs.possible_knight_moves(Square::G1, Color::White),
+            Bitboard::from([Square::F3, Square::H3])
+        ); // white right starting
+        assert_eq!(
+            gs.possible_knight_moves(Square::C5, Color::White),
+            Bitboard::from([
+                Square::A4,
+                Square::E4,
+                Square::A6,
+                Square::E6,
+                Square::B3,
+                Square::D3,
+                Square::B7,
+                Square::D7
+            ]),
             "Failed normal move"
         );
-        assert_eq!(gs.possible_knight_moves(62, false), 1 << 45 | 1 << 47); // black right starting
+        assert_eq!(
+            gs.possible_knight_moves(Square::G8, Color::Black),
+            Bitboard::from([Square::F6, Square::H6])
+        ); // black right starting
     }
 
     #[test]
     fn test_king_moves() {
         let mut gs = GameState::new();
-        gs.castling = 0;
-        assert_eq!(gs.possible_king_moves(4, true), 0, "Captured own");
+        gs.castling = CastlingRights::NONE;
+        assert_eq!(
+            gs.possible_king_moves(Square::E1, Color::White),
+            Bitboard::EMPTY,
+            "Captured own"
+        );
 
         assert_eq!(
-            gs.possible_king_moves_ignore_check(4, false),
-            1 << 3 | 1 << 5 | 1 << 11 | 1 << 12 | 1 << 13
+            gs.possible_king_moves_ignore_check(Square::E1, Color::Black),
+            Bitboard::from([Square::D1, Square::F1, Square::D2, Square::E2, Square::F2])
+        );
+        assert_eq!(
+            gs.possible_king_moves(Square::E1, Color::Black),
+            Bitboard::EMPTY,
+            "Moved into check"
         );
-        assert_eq!(gs.possible_king_moves(4, false), 0, "Moved into check");
 
         assert_eq!(
-            gs.possible_king_moves(34, true),
-            1 << 33 | 1 << 35 | 1 << 27 | 1 << 26 | 1 << 25,
+            gs.possible_king_moves(Square::C5, Color::White),
+            Bitboard::from([Square::B5, Square::D5, Square::D4, Square::C4, Square::B4]),
             "Failed normal move"
         );
     }
 
+    /// Ranks and files between two squares, `to` relative to `from`
+    fn distance(from: Square, to: Square) -> (i8, i8) {
+        (
+            to.rank() as i8 - from.rank() as i8,
+            to.file() as i8 - from.file() as i8,
+        )
+    }
+
+    /// Squares `to` for which `geometry` holds, measured from `from`
+    fn squares_where(from: Square, geometry: impl Fn(i8, i8) -> bool) -> Bitboard {
+        Square::all()
+            .filter(|&to| {
+                let (d_rank, d_file) = distance(from, to);
+                geometry(d_rank, d_file)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_no_edge_wraparound() {
+        let mut gs = GameState::new();
+        gs.castling = CastlingRights::NONE;
+
+        for square in Square::all() {
+            gs.board = Chessboard::empty();
+            assert_eq!(
+                gs.possible_king_moves_ignore_check(square, Color::White),
+                squares_where(square, |r, f| r.abs().max(f.abs()) == 1),
+                "King on {square}"
+            );
+
+            for color in [Color::White, Color::Black] {
+                let (ahead, start) = match color {
+                    Color::White => (1, 1),
+                    Color::Black => (-1, 6),
+                };
+
+                // Alone on the board a pawn can only push
+                gs.board = Chessboard::empty();
+                assert_eq!(
+                    gs.possible_pawn_moves(square, color),
+                    squares_where(square, |r, f| f == 0
+                        && (r == ahead || (r == 2 * ahead && square.rank() == start))),
+                    "Pushes from {square}, {color:?}"
+                );
+
+                // Surrounded by enemy pieces it can only capture
+                let enemy = Piece::new(!color, PieceKind::Pawn);
+                *gs.board.piece_bitboard(enemy) = !square.bitboard();
+                assert_eq!(
+                    gs.possible_pawn_moves(square, color),
+                    squares_where(square, |r, f| r == ahead && f.abs() == 1),
+                    "Captures from {square}, {color:?}"
+                );
+            }
+        }
+    }
+
     #[test]
     fn test_castling() {
         let mut gs = GameState::new();
         gs.board = Chessboard::empty();
-        gs.castling = 0b1111;
+        gs.board.white_rooks = Bitboard::from([Square::A1, Square::H1]);
+        gs.board.black_rooks = Bitboard::from([Square::A8, Square::H8]);
+        gs.castling = CastlingRights::ALL;
 
         assert!(
-            gs.possible_king_moves(4, true) & (1 << 6) != 0,
+            gs.possible_king_moves(Square::E1, Color::White)
+                .contains(Square::G1),
             "White king did not castle king side"
         );
         assert!(
-            gs.possible_king_moves(4, true) & (1 << 2) != 0,
+            gs.possible_king_moves(Square::E1, Color::White)
+                .contains(Square::C1),
             "White king did not castle queen side"
         );
         assert!(
-            gs.possible_king_moves(60, false) & (1 << 62 | 1 << 58) != 0,
+            !(gs.possible_king_moves(Square::E8, Color::Black)
+                & Bitboard::from([Square::G8, Square::C8]))
+            .is_empty(),
             "Black king did not castle"
         );
 
-        gs.board.black_rooks = 1 << 13;
-        gs.castling = 0b1001;
+        gs.board.black_rooks = Square::F2.bitboard();
+        gs.castling = CastlingRights::WHITE_KINGSIDE | CastlingRights::BLACK_QUEENSIDE;
         assert!(
-            gs.possible_king_moves(4, true) & (1 << 6) == 0,
+            !gs.possible_king_moves(Square::E1, Color::White)
+                .contains(Square::G1),
             "Castled through attack"
         );
         assert!(
-            gs.possible_king_moves(4, true) & (1 << 2) == 0,
+            !gs.possible_king_moves(Square::E1, Color::White)
+                .contains(Square::C1),
             "White castled after piece moved"
         );
         assert!(
-            gs.possible_king_moves(60, false) & (1 << 62) == 0,
+            !gs.possible_king_moves(Square::E8, Color::Black)
+                .contains(Square::G8),
             "Black castled after piece moved"
         );
     }
 
     #[test]
-    fn test_move_until_piece() {
-        let gs = GameState::new();
-        let itr = (18..=58).step_by(8);
+    fn test_possible_moves() {
+        let mut gs = GameState::new();
+        assert_eq!(
+            gs.possible_moves(Square::B2),
+            gs.possible_pawn_moves(Square::B2, Color::White)
+        ); // pawn begin
+        assert_eq!(gs.possible_moves(Square::C6), Bitboard::EMPTY); // nothing here
+        gs.board.white_pawns |= Square::C6.bitboard();
+        assert_eq!(
+            gs.possible_moves(Square::C6),
+            gs.possible_pawn_moves(Square::C6, Color::White)
+        ); // pawn eat
+        assert_eq!(
+            gs.possible_moves(Square::C7),
+            gs.possible_pawn_moves(Square::C7, Color::Black)
+        ); // black pawn blocked
+
+        gs.board.white_pawns = Bitboard::EMPTY;
+        gs.board.black_pawns = Bitboard::EMPTY;
+        assert_eq!(
+            gs.possible_moves(Square::A1),
+            gs.possible_rook_moves(Square::A1, Color::White)
+        );
+        assert_eq!(
+            gs.possible_moves(Square::A8),
+            gs.possible_rook_moves(Square::A8, Color::Black)
+        );
+        assert_eq!(
+            gs.possible_moves(Square::B1),
+            gs.possible_knight_moves(Square::B1, Color::White)
+        );
         assert_eq!(
-            gs.move_until_piece(itr.clone(), true),
-            1 << 18 | 1 << 26 | 1 << 34 | 1 << 42 | 1 << 50,
-            "White should move in straight line to black"
+            gs.possible_moves(Square::B8),
+            gs.possible_knight_moves(Square::B8, Color::Black)
         );
         assert_eq!(
-            gs.move_until_piece(itr, false),
-            1 << 18 | 1 << 26 | 1 << 34 | 1 << 42,
-            "Black should move to next black"
+            gs.possible_moves(Square::C1),
+            gs.possible_bishop_moves(Square::C1, Color::White)
         );
+        assert_eq!(
+            gs.possible_moves(Square::C8),
+            gs.possible_bishop_moves(Square::C8, Color::Black)
+        );
+        assert_eq!(
+            gs.possible_moves(Square::D1),
+            gs.possible_queen_moves(Square::D1, Color::White)
+        );
+        assert_eq!(
+            gs.possible_moves(Square::D8),
+            gs.possible_queen_moves(Square::D8, Color::Black)
+        );
+        assert_eq!(
+            gs.possible_moves(Square::E1),
+            gs.possible_king_moves(Square::E1, Color::White)
+        );
+        assert_eq!(
+            gs.possible_moves(Square::E8),
+            gs.possible_king_moves(Square::E8, Color::Black)
+        );
+    }
 
-        assert_eq!(gs.move_until_piece(0..7, true), 0);
-        assert_eq!(gs.move_until_piece(0..7, false), 1); // can eat this piece
+    #[test]
+    fn test_aligned() {
+        assert!(aligned(Square::A1, Square::H1), "Same rank");
+        assert!(aligned(Square::E1, Square::E8), "Same file");
+        assert!(aligned(Square::A1, Square::H8), "Same diagonal");
+        assert!(aligned(Square::H1, Square::A8), "Same anti-diagonal");
+        assert!(!aligned(Square::E1, Square::F3), "Knight jump away");
+        assert!(!aligned(Square::H1, Square::A2), "Wrapped diagonal");
     }
 
     #[test]
-    fn test_possible_moves() {
-        let mut gs = GameState::new();
-        assert_eq!(gs.possible_moves(9), gs.possible_pawn_moves(9, true)); // pawn begin
-        assert_eq!(gs.possible_moves(42), 0); // nothing here
-        gs.board.white_pawns |= 1 << 42;
-        assert_eq!(gs.possible_moves(42), gs.possible_pawn_moves(42, true)); // pawn eat
-        assert_eq!(gs.possible_moves(50), gs.possible_pawn_moves(50, false)); // black pawn blocked
-
-        gs.board.white_pawns = 0;
-        gs.board.black_pawns = 0;
-        assert_eq!(gs.possible_moves(0), gs.possible_rook_moves(0, true));
-        assert_eq!(gs.possible_moves(56), gs.possible_rook_moves(56, false));
-        assert_eq!(gs.possible_moves(1), gs.possible_knight_moves(1, true));
-        assert_eq!(gs.possible_moves(57), gs.possible_knight_moves(57, false));
-        assert_eq!(gs.possible_moves(2), gs.possible_bishop_moves(2, true));
-        assert_eq!(gs.possible_moves(58), gs.possible_bishop_moves(58, false));
-        assert_eq!(gs.possible_moves(3), gs.possible_queen_moves(3, true));
-        assert_eq!(gs.possible_moves(59), gs.possible_queen_moves(59, false));
-        assert_eq!(gs.possible_moves(4), gs.possible_king_moves(4, true));
-        assert_eq!(gs.possible_moves(60), gs.possible_king_moves(60, false));
+    fn test_pinned_piece() {
+        // E2 knight pinned by the E8 rook, D2 bishop pinned by the A5 queen
+        let gs = GameState::from_string("4r2k/8/8/q7/8/8/3BN3/4K3 w - - 0 1").unwrap();
+        assert_eq!(
+            gs.possible_moves(Square::E2),
+            Bitboard::EMPTY,
+            "Pinned knight moved"
+        );
+        assert_eq!(
+            gs.possible_moves(Square::D2),
+            Bitboard::from([Square::C3, Square::B4, Square::A5]),
+            "Pinned bishop left the pin"
+        );
+    }
+
+    #[test]
+    fn test_check_evasion() {
+        // Rook on E8 checks the king, the knight can only block or capture
+        let gs = GameState::from_string("4r2k/8/8/8/8/2N5/8/R3K3 w Q - 0 1").unwrap();
+        assert_eq!(
+            gs.possible_moves(Square::C3),
+            Bitboard::from([Square::E2, Square::E4]),
+            "Knight ignored check"
+        );
+        assert_eq!(
+            gs.possible_moves(Square::A1),
+            Bitboard::EMPTY,
+            "Rook ignored check"
+        );
+        assert_eq!(
+            gs.possible_moves(Square::E1),
+            Bitboard::from([Square::D1, Square::F1, Square::D2, Square::F2]),
+            "King stayed on the checking file or castled"
+        );
+
+        // Double check leaves only king moves
+        let gs = GameState::from_string("4r2k/8/8/8/7b/8/2N5/R3K3 w - - 0 1").unwrap();
+        assert_eq!(
+            gs.possible_moves(Square::C2),
+            Bitboard::EMPTY,
+            "Knight moved in double check"
+        );
+        assert_eq!(
+            gs.possible_moves(Square::A1),
+            Bitboard::EMPTY,
+            "Rook moved in double check"
+        );
+        assert_eq!(
+            gs.possible_moves(Square::E1),
+            Bitboard::from([Square::D1, Square::F1, Square::D2]),
+            "King moves"
+        );
+    }
+
+    #[test]
+    fn test_en_passant_discovered_check() {
+        // Taking en passant would clear the fifth rank between the king and rook
+        let gs = GameState::from_string("8/8/8/K2Pp2r/8/8/8/7k w - e6 0 1").unwrap();
+        assert_eq!(
+            gs.possible_moves(Square::D5),
+            Square::D6.bitboard(),
+            "En passant exposed king"
+        );
+
+        let gs = GameState::from_string("8/8/8/K2Pp3/8/8/8/7k w - e6 0 1").unwrap();
+        assert_eq!(
+            gs.possible_moves(Square::D5),
+            Bitboard::from([Square::D6, Square::E6])
+        );
+    }
+
+    #[test]
+    fn test_possible_move_list() {
+        let gs = GameState::new();
+        let targets: Vec<Square> = gs
+            .possible_move_list(Square::E2)
+            .iter()
+            .map(|m| m.to)
+            .collect();
+        assert_eq!(targets, vec![Square::E3, Square::E4]);
+        assert!(gs.possible_move_list(Square::E4).is_empty());
+
+        let gs = GameState::from_string("1r5k/P7/8/8/8/8/8/K7 w - - 0 1").unwrap();
+        let moves = gs.possible_move_list(Square::A7);
+        assert_eq!(moves.len(), 8, "Expected 4 promotions on each square");
+        for p in PROMOTIONS {
+            assert!(moves
+                .iter()
+                .any(|m| m.to == Square::A8 && m.promotion == Some(p)));
+            assert!(moves.iter().any(|m| m.to == Square::B8
+                && m.promotion == Some(p)
+                && m.captured == Piece::from_char('r')));
+        }
+    }
+
+    #[test]
+    fn test_capture_moves() {
+        assert!(GameState::new().capture_moves().is_empty());
+
+        // Kiwipete has 8 captures at depth 1
+        let gs = GameState::from_string(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        )
+        .unwrap();
+        let captures = gs.capture_moves();
+        assert_eq!(captures.len(), 8);
+        assert!(captures.iter().all(|m| m.is_capture()));
+
+        // Quiet promotions and en passant, but not the pinned rook taking the knight
+        let gs = GameState::from_string("4k3/P7/8/1n1pP3/8/2b5/1R6/K7 w - d6 0 1").unwrap();
+        let captures: Vec<String> = gs.capture_moves().iter().map(|m| m.to_string()).collect();
+        assert_eq!(captures, vec!["e5d6", "a7a8q", "a7a8r", "a7a8b", "a7a8n"]);
+    }
+
+    #[test]
+    fn test_legal_moves() {
+        let gs = GameState::new();
+        let moves = gs.legal_moves();
+        assert_eq!(moves.len(), 20);
+        assert!(moves.iter().all(|m| m.is_white()));
+        assert_eq!(moves.iter().filter(|m| m.is_double_push()).count(), 8);
+
+        let gs =
+            GameState::from_string("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1")
+                .unwrap();
+        assert!(gs.legal_moves().iter().all(|m| !m.is_white()));
+
+        // Checkmated
+        let gs = GameState::from_string("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1").unwrap();
+        assert!(gs.legal_moves().is_empty());
     }
 
     #[test]
@@ -681,90 +919,234 @@ mod tests {
         let mut gs = GameState::new();
         gs.board = Chessboard::empty();
 
-        assert!(!gs.position_under_attack(9, true), "Attacked by nothing");
+        assert!(
+            !gs.position_under_attack(Square::B2, Color::White),
+            "Attacked by nothing"
+        );
 
-        gs.board.black_pawns = 1 << 16;
-        assert!(gs.position_under_attack(9, true), "Pawn not attacking");
-        gs.board.black_pawns = 1 << 2;
+        gs.board.black_pawns = Square::A3.bitboard();
+        assert!(
+            gs.position_under_attack(Square::B2, Color::White),
+            "Pawn not attacking"
+        );
+        gs.board.black_pawns = Square::C1.bitboard();
         assert!(
-            !gs.position_under_attack(9, true),
+            !gs.position_under_attack(Square::B2, Color::White),
             "Black pawn attacking backwards"
         );
-        gs.board.black_pawns = 0;
+        gs.board.black_pawns = Bitboard::EMPTY;
 
-        gs.board.black_queen = 1 << 57;
-        assert!(gs.position_under_attack(9, true), "Queen not attacking");
-        gs.board.black_queen = 0;
+        gs.board.black_queen = Square::B8.bitboard();
+        assert!(
+            gs.position_under_attack(Square::B2, Color::White),
+            "Queen not attacking"
+        );
+        gs.board.black_queen = Bitboard::EMPTY;
 
-        gs.board.black_rooks = 1 << 49;
-        assert!(gs.position_under_attack(9, true), "Rook not attacking");
-        gs.board.black_rooks = 0;
+        gs.board.black_rooks = Square::B7.bitboard();
+        assert!(
+            gs.position_under_attack(Square::B2, Color::White),
+            "Rook not attacking"
+        );
+        gs.board.black_rooks = Bitboard::EMPTY;
 
-        gs.board.black_bishops = 1 << 18;
-        assert!(gs.position_under_attack(9, true), "Bishop not attacking");
-        gs.board.black_bishops = 0;
+        gs.board.black_bishops = Square::C3.bitboard();
+        assert!(
+            gs.position_under_attack(Square::B2, Color::White),
+            "Bishop not attacking"
+        );
+        gs.board.black_bishops = Bitboard::EMPTY;
 
-        gs.board.black_knights = 1 << 26;
-        assert!(gs.position_under_attack(9, true), "Knight not attacking");
+        gs.board.black_knights = Square::C4.bitboard();
+        assert!(
+            gs.position_under_attack(Square::B2, Color::White),
+            "Knight not attacking"
+        );
     }
 
     #[test]
     fn test_move_piece() {
         let mut gs = GameState::new();
 
-        gs.move_piece(12, 28);
+        gs.move_piece(Square::E2, Square::E4);
         assert_eq!(
-            gs.board.piece_at_position(12),
+            gs.board.piece_at_position(Square::E2),
             None,
             "Pawn still exists in old position"
         );
         assert_eq!(
-            gs.board.piece_at_position(28),
-            Some('P'),
+            gs.board.piece_at_position(Square::E4),
+            Piece::from_char('P'),
             "Pawn does not exist in new position"
         );
 
-        gs.move_piece(28, 52);
+        gs.move_piece(Square::E4, Square::E7);
         assert!(
-            gs.board.black_pawns & (1 << 52) == 0,
+            !gs.board.black_pawns.contains(Square::E7),
             "Captured piece not overwritten"
         );
 
         assert_eq!(
-            gs.board.piece_at_position(24),
+            gs.board.piece_at_position(Square::A4),
+            None,
+            "A4 should be empty for the next test to pass"
+        );
+        gs.move_piece(Square::A4, Square::A5);
+        assert_eq!(
+            gs.board.piece_at_position(Square::A5),
             None,
-            "24 should be empty for the next test to pass"
+            "Empty square moved"
         );
-        gs.move_piece(24, 32);
-        assert_eq!(gs.board.piece_at_position(32), None, "Empty square moved");
     }
 
     #[test]
     fn test_move_piece_legally() {
         let mut gs = GameState::new();
-        let mve = gs.move_piece_legally(12, 28);
+        let mve = gs.move_piece_legally(Square::E2, Square::E4, None);
 
-        assert!(mve.is_ok(), "Expected white pawn to move 12 -> 28");
+        assert!(mve.is_ok(), "Expected white pawn to move E2 -> E4");
         assert_eq!(
-            gs.board.piece_at_position(12),
+            gs.board.piece_at_position(Square::E2),
             None,
             "Pawn still exists in old position"
         );
         assert_eq!(
-            gs.board.piece_at_position(28),
-            Some('P'),
+            gs.board.piece_at_position(Square::E4),
+            Piece::from_char('P'),
             "Pawn does not exist in new position"
         );
 
-        let illegal_move = gs.move_piece_legally(28, 12);
+        let illegal_move = gs.move_piece_legally(Square::E4, Square::E2, None);
         assert!(illegal_move.is_err(), "Pawn illegally moved backwards");
 
         assert_eq!(
-            gs.board.piece_at_position(24),
+            gs.board.piece_at_position(Square::A4),
             None,
-            "24 should be emtpy for next test to pass"
+            "A4 should be emtpy for next test to pass"
         );
-        let illegal_move = gs.move_piece_legally(24, 16);
+        let illegal_move = gs.move_piece_legally(Square::A4, Square::A3, None);
         assert!(illegal_move.is_err(), "Empty square moved");
+
+        let illegal_move = gs.move_piece_legally(Square::D2, Square::D3, None);
+        assert!(illegal_move.is_err(), "White moved twice");
+
+        let mut gs = GameState::from_string("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        assert!(
+            gs.move_piece_legally(Square::E1, Square::G1, None).is_ok(),
+            "Could not castle"
+        );
+        assert_eq!(
+            gs.board.piece_at_position(Square::F1),
+            Piece::from_char('R'),
+            "Rook not moved"
+        );
+        assert_eq!(
+            gs.board.piece_at_position(Square::H1),
+            None,
+            "Rook left in corner"
+        );
+
+        let mut gs = GameState::from_string("7k/P7/8/8/8/8/8/K7 w - - 0 1").unwrap();
+        assert!(
+            gs.move_piece_legally(Square::A7, Square::A8, None).is_err(),
+            "Promoted to pawn"
+        );
+        assert!(
+            gs.move_piece_legally(Square::A7, Square::A8, Some(PieceKind::King))
+                .is_err(),
+            "Promoted to king"
+        );
+        assert!(
+            gs.move_piece_legally(Square::A1, Square::B1, Some(PieceKind::Queen))
+                .is_err(),
+            "King promoted"
+        );
+        assert!(gs
+            .move_piece_legally(Square::A7, Square::A8, Some(PieceKind::Rook))
+            .is_ok());
+        assert_eq!(
+            gs.board.piece_at_position(Square::A8),
+            Piece::from_char('R')
+        );
+    }
+
+    #[test]
+    fn test_moves_stay_on_the_board() {
+        let moves = |fen| {
+            let gs = GameState::from_string(fen).unwrap();
+            let moves = gs.legal_moves().into_iter().map(|m| m.to_string());
+            moves.collect::<Vec<_>>()
+        };
+
+        // Pawns on the A and H files can't capture across the board
+        let white = moves("4k3/8/8/8/P6p/8/8/4K3 w - - 0 1");
+        assert!(!white.contains(&"a4h4".to_string()), "{white:?}");
+        let black = moves("4k3/8/8/8/P6p/8/8/4K3 b - - 0 1");
+        assert!(!black.contains(&"h4a4".to_string()), "{black:?}");
+
+        // Nor can kings step off one side onto the other
+        let king = moves("4k3/8/8/8/8/8/8/7K w - - 0 1");
+        assert_eq!(king.len(), 3, "{king:?}");
+        let king = moves("4k3/8/8/8/8/8/K7/8 w - - 0 1");
+        assert_eq!(king.len(), 5, "{king:?}");
+
+        // A bishop near the edge stops at the edge
+        let bishop = moves("4k3/8/8/8/8/8/8/2B4K w - - 0 1");
+        assert!(!bishop.contains(&"c1a1".to_string()), "{bishop:?}");
+        assert_eq!(bishop.iter().filter(|m| m.starts_with("c1")).count(), 7);
+    }
+
+    #[test]
+    fn test_castling_needs_king_and_rook_at_home() {
+        // Rights left over from a position where the pieces were still at home
+        let gs = GameState::from_string("4r2k/8/8/8/8/8/8/4K3 b k - 0 1").unwrap();
+        assert!(!gs.legal_moves().iter().any(|mv| mv.is_castle()));
+
+        let mut gs = GameState::from_string("4k3/8/8/8/8/8/8/R2K3R w KQ - 0 1").unwrap();
+        assert!(!gs.legal_moves().iter().any(|mv| mv.is_castle()));
+        assert!(gs.move_piece_legally(Square::D1, Square::G1, None).is_err());
+
+        let gs = GameState::from_string("4k3/8/8/8/8/8/8/4K2B w K - 0 1").unwrap();
+        assert!(
+            !gs.legal_moves().iter().any(|mv| mv.is_castle()),
+            "No rook on h1"
+        );
+    }
+
+    #[test]
+    fn test_illegal_move_reasons() {
+        let reason = |fen, from, to, promotion| match GameState::from_string(fen)
+            .unwrap()
+            .move_piece_legally(from, to, promotion)
+        {
+            Err(Error::IllegalMove { reason, .. }) => reason,
+            other => panic!("Expected an illegal move, got {other:?}"),
+        };
+        let fen = "7k/P7/8/8/8/8/4p3/K7 w - - 0 1";
+
+        assert_eq!(
+            reason(fen, Square::B2, Square::B3, None),
+            IllegalMove::NoPiece
+        );
+        assert_eq!(
+            reason(fen, Square::E2, Square::E1, Some(PieceKind::Queen)),
+            IllegalMove::OutOfTurn
+        );
+        assert_eq!(
+            reason(fen, Square::A1, Square::A3, None),
+            IllegalMove::NotReachable
+        );
+        assert_eq!(
+            reason(fen, Square::A7, Square::A8, None),
+            IllegalMove::MissingPromotion
+        );
+        assert_eq!(
+            reason(fen, Square::A7, Square::A8, Some(PieceKind::King)),
+            IllegalMove::InvalidPromotion(PieceKind::King)
+        );
+        assert_eq!(
+            reason(fen, Square::A1, Square::B1, Some(PieceKind::Queen)),
+            IllegalMove::NotPromotion
+        );
     }
 }
@@ -0,0 +1,293 @@
+use std::fmt::{self, Display};
+
+use crate::{Bitboard, GameState};
+
+/// Squares A1, C1, ... that a dark squared bishop can reach
+const DARK_SQUARES: Bitboard = Bitboard(0xAA55AA55AA55AA55);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DrawReason {
+    Stalemate,
+    InsufficientMaterial,
+    FiftyMoveRule,       // claimable
+    SeventyFiveMoveRule, // automatic
+    ThreefoldRepetition, // claimable
+    FivefoldRepetition,  // automatic
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw(DrawReason),
+}
+
+impl Display for DrawReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DrawReason::Stalemate => write!(f, "Stalemate"),
+            DrawReason::InsufficientMaterial => write!(f, "Insufficient material"),
+            DrawReason::FiftyMoveRule => write!(f, "Fifty move rule"),
+            DrawReason::SeventyFiveMoveRule => write!(f, "Seventy-five move rule"),
+            DrawReason::ThreefoldRepetition => write!(f, "Threefold repetition"),
+            DrawReason::FivefoldRepetition => write!(f, "Fivefold repetition"),
+        }
+    }
+}
+
+impl Display for GameResult {
+    /// PGN style result with a comment, ex: `1-0 {White mates}`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameResult::WhiteWins => write!(f, "1-0 {{White mates}}"),
+            GameResult::BlackWins => write!(f, "0-1 {{Black mates}}"),
+            GameResult::Draw(reason) => write!(f, "1/2-1/2 {{{reason}}}"),
+        }
+    }
+}
+
+impl GameState {
+    /// Is the side to move in check
+    pub fn is_check(&self) -> bool {
+        match self.king_square(self.side_to_move) {
+            Some(king) => self.position_under_attack(king, self.side_to_move),
+            None => false,
+        }
+    }
+
+    pub fn is_checkmate(&self) -> bool {
+        self.is_check() && !self.has_legal_move()
+    }
+
+    pub fn is_stalemate(&self) -> bool {
+        !self.is_check() && !self.has_legal_move()
+    }
+
+    /// How the game ended, or None if it is still being played
+    /// Only draws that happen automatically end the game, see `claimable_draw`
+    pub fn result(&self) -> Option<GameResult> {
+        if !self.has_legal_move() {
+            return Some(if !self.is_check() {
+                GameResult::Draw(DrawReason::Stalemate)
+            } else if self.side_to_move.is_white() {
+                GameResult::BlackWins
+            } else {
+                GameResult::WhiteWins
+            });
+        }
+
+        if self.is_insufficient_material() {
+            Some(GameResult::Draw(DrawReason::InsufficientMaterial))
+        } else if self.repetition_count() >= 5 {
+            Some(GameResult::Draw(DrawReason::FivefoldRepetition))
+        } else if self.half_clock >= 150 {
+            Some(GameResult::Draw(DrawReason::SeventyFiveMoveRule))
+        } else {
+            None
+        }
+    }
+
+    /// A draw the side to move may claim but that doesn't end the game by itself
+    pub fn claimable_draw(&self) -> Option<DrawReason> {
+        if self.repetition_count() >= 3 {
+            Some(DrawReason::ThreefoldRepetition)
+        } else if self.half_clock >= 100 {
+            Some(DrawReason::FiftyMoveRule)
+        } else {
+            None
+        }
+    }
+
+    /// Any draw that applies to this position, automatic or claimable
+    pub fn draw_reason(&self) -> Option<DrawReason> {
+        match self.result() {
+            Some(GameResult::Draw(reason)) => Some(reason),
+            Some(_) => None,
+            None => self.claimable_draw(),
+        }
+    }
+
+    /// How many times this position has occurred, including now
+    /// Only positions since the last capture or pawn move can repeat
+    pub fn repetition_count(&self) -> usize {
+        1 + self
+            .history
+            .iter()
+            .rev()
+            .take(self.half_clock as usize)
+            .skip(1) // the other side was to move
+            .step_by(2)
+            .filter(|undo| undo.hash == self.hash)
+            .count()
+    }
+
+    /// Neither side has enough pieces left to ever checkmate
+    /// K vs K, K + minor vs K, or any number of bishops all on one square colour
+    pub fn is_insufficient_material(&self) -> bool {
+        let b = &self.board;
+        let heavy = b.white_pawns
+            | b.black_pawns
+            | b.white_rooks
+            | b.black_rooks
+            | b.white_queen
+            | b.black_queen;
+        if !heavy.is_empty() {
+            return false;
+        }
+
+        let knights = b.white_knights | b.black_knights;
+        let bishops = b.white_bishops | b.black_bishops;
+        if (knights | bishops).count() <= 1 {
+            return true;
+        }
+
+        knights.is_empty()
+            && ((bishops & DARK_SQUARES).is_empty() || (bishops & !DARK_SQUARES).is_empty())
+    }
+
+    /// Stop at the first legal move instead of generating all of them
+    fn has_legal_move(&self) -> bool {
+        self.board
+            .one_side_pieces(self.side_to_move)
+            .into_iter()
+            .any(|square| !self.possible_moves(square).is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Move, Square};
+
+    #[test]
+    fn test_check() {
+        assert!(!GameState::new().is_check());
+
+        let gs = GameState::from_string("4k3/8/8/8/8/8/8/4R1K1 b - - 0 1").unwrap();
+        assert!(gs.is_check());
+        assert!(!gs.is_checkmate());
+        assert_eq!(gs.result(), None);
+    }
+
+    #[test]
+    fn test_checkmate() {
+        // Fool's mate
+        let gs =
+            GameState::from_string("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3")
+                .unwrap();
+        assert!(gs.is_check());
+        assert!(gs.is_checkmate());
+        assert!(!gs.is_stalemate());
+        assert_eq!(gs.result(), Some(GameResult::BlackWins));
+
+        let gs = GameState::from_string("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1").unwrap();
+        assert_eq!(gs.result(), Some(GameResult::WhiteWins));
+        assert_eq!(gs.result().unwrap().to_string(), "1-0 {White mates}");
+    }
+
+    #[test]
+    fn test_insufficient_material() {
+        let drawn = [
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 1",      // K vs K
+            "4k3/8/8/8/8/8/8/2B1K3 w - - 0 1",    // K+B vs K
+            "4k3/8/8/8/8/8/8/1N2K3 b - - 0 1",    // K+N vs K
+            "2b1k3/8/8/8/8/8/8/3BK3 w - - 0 1",   // bishops on light squares
+            "4k3/8/8/8/8/8/8/B1B1K1B1 w - - 0 1", // bishops on dark squares
+        ];
+        for fen in drawn {
+            let gs = GameState::from_string(fen).unwrap();
+            assert!(gs.is_insufficient_material(), "{fen}");
+            assert_eq!(
+                gs.result(),
+                Some(GameResult::Draw(DrawReason::InsufficientMaterial))
+            );
+        }
+
+        let playable = [
+            "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1",   // pawn
+            "4k3/8/8/8/8/8/8/R3K3 w - - 0 1",    // rook
+            "2b1k3/8/8/8/8/8/8/2B1K3 w - - 0 1", // opposite coloured bishops
+            "4k3/8/8/8/8/8/8/1NB1K3 w - - 0 1",  // knight and bishop
+            "1n2k3/8/8/8/8/8/8/1N2K3 w - - 0 1", // a knight each
+        ];
+        for fen in playable {
+            let gs = GameState::from_string(fen).unwrap();
+            assert!(!gs.is_insufficient_material(), "{fen}");
+            assert_eq!(gs.result(), None);
+        }
+    }
+
+    #[test]
+    fn test_move_rules() {
+        let gs = GameState::from_string("4k3/8/8/8/8/8/4P3/R3K3 w - - 99 80").unwrap();
+        assert_eq!(gs.claimable_draw(), None);
+
+        let gs = GameState::from_string("4k3/8/8/8/8/8/4P3/R3K3 w - - 100 80").unwrap();
+        assert_eq!(gs.claimable_draw(), Some(DrawReason::FiftyMoveRule));
+        assert_eq!(gs.draw_reason(), Some(DrawReason::FiftyMoveRule));
+        assert_eq!(gs.result(), None, "Fifty move rule must be claimed");
+
+        let gs = GameState::from_string("4k3/8/8/8/8/8/4P3/R3K3 w - - 150 80").unwrap();
+        assert_eq!(
+            gs.result(),
+            Some(GameResult::Draw(DrawReason::SeventyFiveMoveRule))
+        );
+
+        // Checkmate takes priority over the seventy-five move rule
+        let gs = GameState::from_string("R5k1/5ppp/8/8/8/8/8/6K1 b - - 150 80").unwrap();
+        assert_eq!(gs.result(), Some(GameResult::WhiteWins));
+    }
+
+    #[test]
+    fn test_repetition() {
+        let mut gs = GameState::new();
+        assert_eq!(gs.repetition_count(), 1);
+
+        // Shuffle knights back and forth
+        let shuffle = [
+            (Square::G1, Square::F3),
+            (Square::G8, Square::F6),
+            (Square::F3, Square::G1),
+            (Square::F6, Square::G8),
+        ];
+        for round in 2..=5 {
+            for (from, to) in shuffle {
+                gs.make_move(Move::new(&gs, from, to, None).unwrap());
+            }
+            assert_eq!(gs.repetition_count(), round);
+        }
+
+        gs.unmake_move();
+        assert_eq!(gs.repetition_count(), 4, "Position after Nf3 Nf6 Ng1");
+        gs.make_move(Move::new(&gs, Square::F6, Square::G8, None).unwrap());
+
+        assert_eq!(
+            gs.result(),
+            Some(GameResult::Draw(DrawReason::FivefoldRepetition))
+        );
+
+        // Three times is only claimable
+        let mut gs = GameState::new();
+        for _ in 0..2 {
+            for (from, to) in shuffle {
+                gs.make_move(Move::new(&gs, from, to, None).unwrap());
+            }
+        }
+        assert_eq!(gs.result(), None);
+        assert_eq!(gs.claimable_draw(), Some(DrawReason::ThreefoldRepetition));
+
+        // A pawn move means nothing before it can repeat
+        gs.make_move(Move::new(&gs, Square::E2, Square::E4, None).unwrap());
+        assert_eq!(gs.repetition_count(), 1);
+    }
+
+    #[test]
+    fn test_stalemate() {
+        let gs = GameState::from_string("4k3/4P3/4K3/8/8/8/8/8 b - - 0 1").unwrap();
+        assert!(!gs.is_check());
+        assert!(gs.is_stalemate());
+        assert!(!gs.is_checkmate());
+        assert_eq!(gs.result(), Some(GameResult::Draw(DrawReason::Stalemate)));
+        assert_eq!(gs.result().unwrap().to_string(), "1/2-1/2 {Stalemate}");
+    }
+}
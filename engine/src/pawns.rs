@@ -0,0 +1,274 @@
+use std::mem;
+
+use crate::attacks::all_pawn_attacks;
+use crate::{Bitboard, Chessboard, Color, GameState, Piece, PieceKind, Square};
+
+// https://www.chessprogramming.org/Pawn_Structure
+
+/// Size of the pawn table used by a search
+pub const DEFAULT_PAWN_HASH_MB: usize = 1;
+
+/// A bonus (or penalty when negative) in centipawns for the middlegame and the endgame
+pub type Weight = (i64, i64);
+
+/// How much each pawn structure feature is worth, per pawn
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PawnWeights {
+    pub passed: [Weight; 8], // by rank counted from the pawn's own side, 0 and 7 are never used
+    pub doubled: Weight,     // for every pawn with another of its colour behind it
+    pub isolated: Weight,    // no pawns of its colour on either neighbouring file
+    pub backward: Weight,    // can't be supported and can't advance safely
+    pub connected: Weight,   // defended by a pawn or standing next to one
+}
+
+impl Default for PawnWeights {
+    fn default() -> Self {
+        Self {
+            passed: [
+                (0, 0),
+                (0, 10),
+                (5, 15),
+                (10, 25),
+                (20, 45),
+                (35, 70),
+                (60, 110),
+                (0, 0),
+            ],
+            doubled: (-10, -20),
+            isolated: (-10, -15),
+            backward: (-8, -10),
+            connected: (8, 5),
+        }
+    }
+}
+
+pub(crate) fn file_mask(square: Square) -> Bitboard {
+    Bitboard::file(square.file())
+}
+
+pub(crate) fn neighbouring_files(square: Square) -> Bitboard {
+    let file = file_mask(square);
+    file.west() | file.east()
+}
+
+/// Every square on a rank ahead of `square` from `color`'s point of view
+fn ranks_ahead(square: Square, color: Color) -> Bitboard {
+    let rank = square.rank();
+    match (color, rank) {
+        (Color::White, 7) | (Color::Black, 0) => Bitboard::EMPTY,
+        (Color::White, _) => Bitboard::FULL << ((rank as u32 + 1) * 8),
+        (Color::Black, _) => Bitboard::FULL >> ((8 - rank as u32) * 8),
+    }
+}
+
+/// Keep only the squares of `pawns` for which `test` holds
+fn filter_squares(pawns: Bitboard, test: impl Fn(Square) -> bool) -> Bitboard {
+    pawns.into_iter().filter(|&square| test(square)).collect()
+}
+
+impl Chessboard {
+    fn pawns(&self, color: Color) -> Bitboard {
+        self.pieces(Piece::new(color, PieceKind::Pawn))
+    }
+
+    /// Pawns with no enemy pawns in front of them on their own or neighbouring files
+    pub fn passed_pawns(&self, color: Color) -> Bitboard {
+        let enemy = self.pawns(!color);
+        filter_squares(self.pawns(color), |sq| {
+            let span = (file_mask(sq) | neighbouring_files(sq)) & ranks_ahead(sq, color);
+            (enemy & span).is_empty()
+        })
+    }
+
+    /// Pawns with another pawn of the same colour behind them on the file
+    pub fn doubled_pawns(&self, color: Color) -> Bitboard {
+        let own = self.pawns(color);
+        filter_squares(own, |sq| {
+            !(own & file_mask(sq) & ranks_ahead(sq, !color)).is_empty()
+        })
+    }
+
+    /// Pawns with no pawns of the same colour on either neighbouring file
+    pub fn isolated_pawns(&self, color: Color) -> Bitboard {
+        let own = self.pawns(color);
+        filter_squares(own, |sq| (own & neighbouring_files(sq)).is_empty())
+    }
+
+    /// Pawns behind all their neighbours whose next square is covered by an enemy pawn
+    pub fn backward_pawns(&self, color: Color) -> Bitboard {
+        let own = self.pawns(color);
+        let enemy_attacks = all_pawn_attacks(self.pawns(!color), !color);
+        filter_squares(own & !self.isolated_pawns(color), |sq| {
+            let supporters = own & neighbouring_files(sq) & !ranks_ahead(sq, color);
+            let stop = match color {
+                Color::White => sq.bitboard().north(),
+                Color::Black => sq.bitboard().south(),
+            };
+            supporters.is_empty() && !(enemy_attacks & stop).is_empty()
+        })
+    }
+
+    /// Pawns defended by a pawn or standing beside one on the same rank
+    pub fn connected_pawns(&self, color: Color) -> Bitboard {
+        let own = self.pawns(color);
+        let defended = own & all_pawn_attacks(own, color);
+        let beside = own & (own.west() | own.east());
+        defended | beside
+    }
+
+    /// Middlegame and endgame pawn structure score, positive when white's is better
+    pub fn pawn_structure(&self, weights: &PawnWeights) -> Weight {
+        let (mut mg, mut eg) = (0, 0);
+
+        for color in [Color::White, Color::Black] {
+            let sign = color.sign();
+            let mut add = |(w_mg, w_eg): Weight, count: u32| {
+                mg += sign * w_mg * count as i64;
+                eg += sign * w_eg * count as i64;
+            };
+
+            for square in self.passed_pawns(color) {
+                let rank = match color {
+                    Color::White => square.rank(),
+                    Color::Black => 7 - square.rank(),
+                };
+                add(weights.passed[rank as usize], 1);
+            }
+            add(weights.doubled, self.doubled_pawns(color).count());
+            add(weights.isolated, self.isolated_pawns(color).count());
+            add(weights.backward, self.backward_pawns(color).count());
+            add(weights.connected, self.connected_pawns(color).count());
+        }
+
+        (mg, eg)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct PawnEntry {
+    key: u64,
+    score: Weight,
+}
+
+/// Cache of pawn structure scores keyed by `GameState::pawn_hash`
+/// Pawns move rarely, so most positions in a search share a handful of structures
+pub struct PawnTable {
+    entries: Vec<Option<PawnEntry>>,
+    weights: PawnWeights,
+}
+
+impl Default for PawnTable {
+    fn default() -> Self {
+        Self::new(DEFAULT_PAWN_HASH_MB, PawnWeights::default())
+    }
+}
+
+impl PawnTable {
+    /// A table using about `megabytes` of memory, scoring with `weights`
+    pub fn new(megabytes: usize, weights: PawnWeights) -> Self {
+        let count = (megabytes * 1024 * 1024 / mem::size_of::<Option<PawnEntry>>()).max(1);
+        Self {
+            entries: vec![None; count],
+            weights,
+        }
+    }
+
+    pub fn weights(&self) -> &PawnWeights {
+        &self.weights
+    }
+
+    /// Pawn structure score of this position, only worked out if it isn't cached
+    pub fn pawn_structure(&mut self, gs: &GameState) -> Weight {
+        let key = gs.pawn_hash();
+        let index = (key % self.entries.len() as u64) as usize;
+        if let Some(entry) = self.entries[index].filter(|e| e.key == key) {
+            return entry.score;
+        }
+
+        let score = gs.board.pawn_structure(&self.weights);
+        self.entries[index] = Some(PawnEntry { key, score });
+        score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Color::{Black, White};
+
+    fn board(placement: &str) -> Chessboard {
+        Chessboard::from_string(placement).unwrap()
+    }
+
+    #[test]
+    fn test_passed_pawns() {
+        // a5 and h4 are passed, d4 has the c5 pawn in front of it on a neighbouring file
+        let b = board("4k3/8/8/P1p5/3P3P/8/8/4K3");
+        assert_eq!(
+            b.passed_pawns(White),
+            Bitboard::from([Square::A5, Square::H4])
+        );
+        assert!(b.passed_pawns(Black).is_empty(), "d4 is in front of c5");
+
+        let b = board("4k3/8/8/8/8/p7/8/4K3");
+        assert_eq!(b.passed_pawns(Black), Square::A3.bitboard());
+    }
+
+    #[test]
+    fn test_doubled_and_isolated_pawns() {
+        let b = board("4k3/8/8/8/4P3/4P3/PP2P3/4K3");
+        assert_eq!(
+            b.doubled_pawns(White),
+            Bitboard::from([Square::E3, Square::E4]),
+            "Front two e pawns"
+        );
+        assert_eq!(
+            b.isolated_pawns(White),
+            Bitboard::from([Square::E2, Square::E3, Square::E4])
+        );
+        assert!(b.isolated_pawns(Black).is_empty());
+    }
+
+    #[test]
+    fn test_backward_pawns() {
+        // d3 is behind its neighbours c4 and e4, and d4 is covered by the c5 pawn
+        let b = board("4k3/8/8/2p5/2P1P3/3P4/8/4K3");
+        assert_eq!(b.backward_pawns(White), Square::D3.bitboard());
+
+        // Without the black pawn it can advance safely
+        let b = board("4k3/8/8/8/2P1P3/3P4/8/4K3");
+        assert!(b.backward_pawns(White).is_empty());
+    }
+
+    #[test]
+    fn test_connected_pawns() {
+        // f2 defends e3 and g3, a2 and b2 stand side by side, h5 is alone
+        let b = board("4k3/8/8/7P/8/4P1P1/PP3P2/4K3");
+        assert_eq!(
+            b.connected_pawns(White),
+            Bitboard::from([Square::A2, Square::B2, Square::E3, Square::G3])
+        );
+    }
+
+    #[test]
+    fn test_pawn_structure() {
+        let weights = PawnWeights::default();
+        assert_eq!(Chessboard::new().pawn_structure(&weights), (0, 0));
+
+        // A healthy chain against doubled isolated pawns
+        let good = board("4k3/pp6/8/8/8/8/5PPP/4K3");
+        let bad = board("4k3/pp6/8/8/8/7P/7P/4K3");
+        let (good_mg, good_eg) = good.pawn_structure(&weights);
+        let (bad_mg, bad_eg) = bad.pawn_structure(&weights);
+        assert!(good_mg > bad_mg && good_eg > bad_eg);
+    }
+
+    #[test]
+    fn test_pawn_table() {
+        let mut table = PawnTable::new(1, PawnWeights::default());
+        let gs = GameState::from_string("4k3/pp6/8/8/8/7P/7P/4K3 w - - 0 1").unwrap();
+        let expected = gs.board.pawn_structure(table.weights());
+        assert_eq!(table.pawn_structure(&gs), expected);
+        assert_eq!(table.pawn_structure(&gs), expected, "Cached score changed");
+    }
+}
@@ -0,0 +1,102 @@
+use crate::{GameState, Move};
+
+impl GameState {
+    /// Count the leaf nodes of the legal move tree `depth` plies deep
+    /// https://www.chessprogramming.org/Perft
+    pub fn perft(&mut self, depth: u32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+
+        let moves = self.legal_moves();
+        if depth == 1 {
+            return moves.len() as u64;
+        }
+
+        let mut nodes = 0;
+        for mv in moves {
+            self.make_move(mv);
+            nodes += self.perft(depth - 1);
+            self.unmake_move();
+        }
+        nodes
+    }
+
+    /// Perft split by the first move, for finding which branch disagrees
+    pub fn divide(&mut self, depth: u32) -> Vec<(Move, u64)> {
+        let mut result = Vec::new();
+        if depth == 0 {
+            return result;
+        }
+
+        for mv in self.legal_moves() {
+            self.make_move(mv);
+            result.push((mv, self.perft(depth - 1)));
+            self.unmake_move();
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // https://www.chessprogramming.org/Perft_Results
+    const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+    const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+    const POSITION_3: &str = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1";
+    const POSITION_4: &str = "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1";
+    const POSITION_5: &str = "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8";
+    const POSITION_6: &str =
+        "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10";
+
+    fn check_perft(fen: &str, expected: &[u64]) {
+        let mut gs = GameState::from_string(fen).unwrap();
+        for (depth, &nodes) in expected.iter().enumerate() {
+            let depth = depth as u32 + 1;
+            assert_eq!(gs.perft(depth), nodes, "{fen} at depth {depth}");
+        }
+        assert_eq!(gs.to_string(), fen, "Perft did not restore the position");
+    }
+
+    #[test]
+    fn test_perft_start() {
+        check_perft(START, &[20, 400, 8902]);
+    }
+
+    #[test]
+    fn test_perft_kiwipete() {
+        check_perft(KIWIPETE, &[48, 2039, 97862]);
+    }
+
+    #[test]
+    fn test_perft_position_3() {
+        check_perft(POSITION_3, &[14, 191, 2812, 43238]);
+    }
+
+    #[test]
+    fn test_perft_position_4() {
+        check_perft(POSITION_4, &[6, 264, 9467]);
+    }
+
+    #[test]
+    fn test_perft_position_5() {
+        check_perft(POSITION_5, &[44, 1486, 62379]);
+    }
+
+    #[test]
+    fn test_perft_position_6() {
+        check_perft(POSITION_6, &[46, 2079, 89890]);
+    }
+
+    #[test]
+    fn test_divide() {
+        let mut gs = GameState::from_string(START).unwrap();
+        let divided = gs.divide(2);
+        assert_eq!(divided.len(), 20);
+        assert!(divided.iter().all(|(_, nodes)| *nodes == 20));
+        assert_eq!(divided.iter().map(|(_, n)| n).sum::<u64>(), gs.perft(2));
+        assert!(gs.divide(0).is_empty());
+    }
+}
@@ -1,58 +1,64 @@
-/// A square is a 0-63 number
-pub fn square_to_rank(square: u8) -> u8 {
-    (square / 8) + 1
+use crate::{Error, PieceKind, Square};
+
+pub fn square_to_rank(square: Square) -> u8 {
+    square.rank() + 1
 }
 
-pub fn square_to_file(square: u8) -> char {
-    ((square % 8) + b'A') as char
+pub fn square_to_file(square: Square) -> char {
+    (square.file() + b'A') as char
 }
 
-pub fn square_to_rank_file(square: u8) -> (u8, char) {
+pub fn square_to_rank_file(square: Square) -> (u8, char) {
     let rank = square_to_rank(square);
     let file = square_to_file(square);
     (rank, file)
 }
 
 #[rustfmt::skip]
-pub fn rank_file_to_square(rank: u8, mut file: char) -> Result<u8, String> {
+pub fn rank_file_to_square(rank: u8, mut file: char) -> Result<Square, Error> {
     file.make_ascii_uppercase();
-    if !(1..=8).contains(&rank)     { return Err(format!("Invalid rank {rank}")); }
-    if !('A'..='H').contains(&file) { return Err(format!("Invalid file {file}")); }
-    Ok((rank - 1) * 8 + (file as u8 - b'A'))
+    if !(1..=8).contains(&rank) || !('A'..='H').contains(&file) {
+        return Err(Error::InvalidSquare(format!("{file}{rank}")));
+    }
+    Ok(Square::from_rank_file(rank - 1, file as u8 - b'A').expect("Checked above"))
 }
 
-pub fn square_to_string(square: u8) -> String {
+pub fn square_to_string(square: Square) -> String {
     let (rank, file) = square_to_rank_file(square);
     format!("{}{}", file, rank)
 }
 
 /// Coordinate string `A1` - `H8`
-pub fn string_to_square(coord: &str) -> Result<u8, String> {
+pub fn string_to_square(coord: &str) -> Result<Square, Error> {
+    let invalid = || Error::InvalidSquare(coord.to_string());
     let chars: Vec<char> = coord.chars().collect();
     if chars.len() != 2 {
-        return Err(format!("Invalid coordinate input: {coord}"));
+        return Err(invalid());
     }
 
-    let rank = chars[1]
-        .to_digit(10)
-        .ok_or_else(|| format!("Invalid rank: {}", chars[1]))? as u8;
+    let rank = chars[1].to_digit(10).ok_or_else(invalid)? as u8;
     let file = chars[0];
-    rank_file_to_square(rank, file)
+    rank_file_to_square(rank, file).map_err(|_| invalid())
 }
 
-/// Find the squares turned on in this bitboard
-pub fn active_squares(mut bitboard: u64) -> Vec<u8> {
-    let mut squares = Vec::new();
-    let mut index = 0;
-
-    while bitboard != 0 {
-        if bitboard & 1 != 0 {
-            squares.push(index);
-        }
-        index += 1;
-        bitboard >>= 1;
+/// Move string `E2:E4`, `E7:E8=Q` or long algebraic `e7e8q`
+/// Returns (from, to, promotion)
+pub fn string_to_move(move_string: &str) -> Result<(Square, Square, Option<PieceKind>), Error> {
+    let compact: String = move_string
+        .chars()
+        .filter(|c| *c != ':' && *c != '=')
+        .collect();
+    if !compact.is_ascii() || !(4..=5).contains(&compact.len()) {
+        return Err(Error::InvalidMoveFormat(move_string.to_string()));
     }
-    squares
+
+    let from = string_to_square(&compact[0..2])?;
+    let to = string_to_square(&compact[2..4])?;
+    let promotion = match compact[4..].chars().next() {
+        None => None,
+        Some(p) => Some(PieceKind::from_char(p).ok_or(Error::InvalidPiece(p))?),
+    };
+    Ok((from, to, promotion))
 }
 
 #[cfg(test)]
@@ -61,35 +67,35 @@ mod tests {
 
     #[test]
     fn test_square_to_rank() {
-        assert_eq!(square_to_rank(0), 1);
-        assert_eq!(square_to_rank(7), 1);
-        assert_eq!(square_to_rank(8), 2);
-        assert_eq!(square_to_rank(63), 8);
+        assert_eq!(square_to_rank(Square::A1), 1);
+        assert_eq!(square_to_rank(Square::H1), 1);
+        assert_eq!(square_to_rank(Square::A2), 2);
+        assert_eq!(square_to_rank(Square::H8), 8);
     }
 
     #[test]
     fn test_square_to_file() {
-        assert_eq!(square_to_file(0), 'A');
-        assert_eq!(square_to_file(7), 'H');
-        assert_eq!(square_to_file(8), 'A');
-        assert_eq!(square_to_file(62), 'G')
+        assert_eq!(square_to_file(Square::A1), 'A');
+        assert_eq!(square_to_file(Square::H1), 'H');
+        assert_eq!(square_to_file(Square::A2), 'A');
+        assert_eq!(square_to_file(Square::G8), 'G')
     }
 
     #[test]
     fn test_to_square() {
-        assert_eq!(rank_file_to_square(1, 'A').unwrap(), 0);
-        assert_eq!(rank_file_to_square(1, 'H').unwrap(), 7);
-        assert_eq!(rank_file_to_square(2, 'A').unwrap(), 8);
-        assert_eq!(rank_file_to_square(8, 'H').unwrap(), 63);
+        assert_eq!(rank_file_to_square(1, 'A').unwrap(), Square::A1);
+        assert_eq!(rank_file_to_square(1, 'H').unwrap(), Square::H1);
+        assert_eq!(rank_file_to_square(2, 'A').unwrap(), Square::A2);
+        assert_eq!(rank_file_to_square(8, 'H').unwrap(), Square::H8);
         assert_eq!(
             rank_file_to_square(1, 'B').unwrap(),
             rank_file_to_square(1, 'b').unwrap()
         );
 
-        assert_eq!(string_to_square("A1").unwrap(), 0);
-        assert_eq!(string_to_square("H1").unwrap(), 7);
-        assert_eq!(string_to_square("A2").unwrap(), 8);
-        assert_eq!(string_to_square("H8").unwrap(), 63);
+        assert_eq!(string_to_square("A1").unwrap(), Square::A1);
+        assert_eq!(string_to_square("H1").unwrap(), Square::H1);
+        assert_eq!(string_to_square("A2").unwrap(), Square::A2);
+        assert_eq!(string_to_square("H8").unwrap(), Square::H8);
         assert_eq!(
             string_to_square("B1").unwrap(),
             string_to_square("b1").unwrap()
@@ -99,12 +105,30 @@ mod tests {
         assert!(rank_file_to_square(1, 'I').is_err());
         assert!(string_to_square("A0").is_err());
         assert!(string_to_square("I1").is_err());
+        assert_eq!(
+            string_to_square("e9"),
+            Err(Error::InvalidSquare("e9".to_string()))
+        );
     }
 
     #[test]
-    fn test_acitve_squares() {
-        assert_eq!(active_squares(0b10101010), vec![1, 3, 5, 7]);
-        assert_eq!(active_squares(0b11100000), vec![5, 6, 7]);
-        assert_eq!(active_squares(0), vec![]);
+    fn test_string_to_move() {
+        let e2e4 = (Square::E2, Square::E4, None);
+        assert_eq!(string_to_move("E2:E4").unwrap(), e2e4);
+        assert_eq!(string_to_move("e2e4").unwrap(), e2e4);
+        assert_eq!(
+            string_to_move("E7:E8=Q").unwrap(),
+            (Square::E7, Square::E8, Some(PieceKind::Queen))
+        );
+        assert_eq!(
+            string_to_move("e7e8n").unwrap(),
+            (Square::E7, Square::E8, Some(PieceKind::Knight))
+        );
+
+        assert!(string_to_move("E2").is_err());
+        assert!(string_to_move("E2:E9").is_err());
+        assert!(string_to_move("E7:E8=QQ").is_err());
+        assert!(string_to_move("é2e4").is_err());
+        assert_eq!(string_to_move("e7e8x"), Err(Error::InvalidPiece('x')));
     }
 }
@@ -0,0 +1,525 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::evaluation::piece_value;
+use crate::pawns::PawnTable;
+use crate::transposition::{Bound, TranspositionTable};
+use crate::{GameState, Move, PieceKind, SearchLimits};
+
+/// Score for delivering checkmate right now, mates further away score slightly less
+pub const MATE: i64 = 1_000_000;
+const INFINITY: i64 = 2 * MATE;
+/// Deepest iteration when nothing else limits the search
+pub const MAX_DEPTH: u32 = 64;
+/// How many nodes to search between looking at the clock and stop flag
+const CHECK_INTERVAL: u64 = 1024;
+/// Captures that can't bring the score within this much of alpha aren't searched
+const DELTA_MARGIN: i64 = 200;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SearchResult {
+    pub best_move: Option<Move>, // None when there are no legal moves
+    pub score: i64,              // from the point of view of the side to move
+    pub pv: Vec<Move>,           // principal variation, starting with `best_move`
+    pub depth: u32,
+    pub nodes: u64,
+    pub time: Duration,
+}
+
+/// Bookkeeping shared by every node of one search
+struct SearchContext<'a> {
+    nodes: u64,
+    elapsed: &'a mut dyn FnMut() -> Duration, // time since the search started
+    budget: Option<Duration>,
+    node_limit: Option<u64>,
+    stop: &'a AtomicBool,
+    table: &'a mut TranspositionTable,
+    pawns: &'a mut PawnTable,
+    can_stop: bool, // false until the first iteration has finished
+    aborted: bool,  // a limit was hit part way through an iteration
+}
+
+impl SearchContext<'_> {
+    fn should_stop(&mut self) -> bool {
+        if self.can_stop && !self.aborted {
+            // Reading the clock is slow, so only do it every so often
+            let out_of_time = self.nodes.is_multiple_of(CHECK_INTERVAL)
+                && self.budget.is_some_and(|b| (self.elapsed)() >= b);
+            self.aborted = out_of_time
+                || self.stop.load(Ordering::Relaxed)
+                || self.node_limit.is_some_and(|n| self.nodes >= n);
+        }
+        self.aborted
+    }
+}
+
+impl GameState {
+    /// Negamax alpha-beta search `depth` plies deep
+    pub fn search(&mut self, depth: u32) -> SearchResult {
+        self.search_with_limits(
+            &SearchLimits::depth(depth),
+            &mut TranspositionTable::default(),
+            &mut PawnTable::default(),
+            &AtomicBool::new(false),
+            |_| (),
+        )
+    }
+
+    /// Iterative deepening: search one ply deeper each iteration until a limit is hit
+    /// or `stop` is set. `report` is called after every completed iteration
+    /// `table` can be kept between searches of the same game to reuse earlier work,
+    /// `pawns` between any searches scored with the same weights
+    /// https://www.chessprogramming.org/Iterative_Deepening
+    pub fn search_with_limits<F>(
+        &mut self,
+        limits: &SearchLimits,
+        table: &mut TranspositionTable,
+        pawns: &mut PawnTable,
+        stop: &AtomicBool,
+        report: F,
+    ) -> SearchResult
+    where
+        F: FnMut(&SearchResult),
+    {
+        let start = Instant::now();
+        let mut elapsed = || start.elapsed();
+        self.search_with_clock(limits, table, pawns, stop, &mut elapsed, report)
+    }
+
+    /// `search_with_limits` timed by `elapsed` rather than the wall clock
+    fn search_with_clock<F>(
+        &mut self,
+        limits: &SearchLimits,
+        table: &mut TranspositionTable,
+        pawns: &mut PawnTable,
+        stop: &AtomicBool,
+        elapsed: &mut dyn FnMut() -> Duration,
+        mut report: F,
+    ) -> SearchResult
+    where
+        F: FnMut(&SearchResult),
+    {
+        let budget = limits.time_budget(self.side_to_move);
+        table.new_search();
+        let mut context = SearchContext {
+            nodes: 0,
+            elapsed,
+            budget,
+            node_limit: limits.nodes,
+            stop,
+            table,
+            pawns,
+            can_stop: false,
+            aborted: false,
+        };
+
+        let max_depth = limits.depth.unwrap_or(MAX_DEPTH).clamp(1, MAX_DEPTH);
+        let mut best = SearchResult {
+            best_move: None,
+            score: 0,
+            pv: Vec::new(),
+            depth: 0,
+            nodes: 0,
+            time: Duration::ZERO,
+        };
+
+        for depth in 1..=max_depth {
+            let mut pv = Vec::new();
+            let score = self.negamax(depth, 0, -INFINITY, INFINITY, &mut pv, &mut context);
+
+            // A cut off iteration can't be trusted
+            if context.aborted {
+                break;
+            }
+            // The first iteration always finishes so there is a move to play
+            context.can_stop = true;
+
+            best = SearchResult {
+                best_move: pv.first().copied(),
+                score,
+                pv,
+                depth,
+                nodes: context.nodes,
+                time: (context.elapsed)(),
+            };
+            report(&best);
+
+            let found_mate = score.abs() > MATE - MAX_DEPTH as i64;
+            // The next iteration takes longer than all the previous ones combined
+            let out_of_time = budget.is_some_and(|b| (context.elapsed)() > b / 2);
+            if found_mate || out_of_time || best.best_move.is_none() {
+                break;
+            }
+        }
+
+        best.nodes = context.nodes;
+        best.time = (context.elapsed)();
+        best
+    }
+
+    /// Score of the position for the side to move, `pv` is filled with the best line
+    fn negamax(
+        &mut self,
+        depth: u32,
+        ply: u32,
+        mut alpha: i64,
+        mut beta: i64,
+        pv: &mut Vec<Move>,
+        context: &mut SearchContext,
+    ) -> i64 {
+        context.nodes += 1;
+        pv.clear();
+
+        if context.should_stop() {
+            return 0;
+        }
+
+        if ply > 0 && (self.claimable_draw().is_some() || self.is_insufficient_material()) {
+            return 0;
+        }
+
+        // Quiescence finds its own mates, so the leaves skip generating every move
+        if depth == 0 {
+            return self.quiescence(ply, alpha, beta, context);
+        }
+
+        // An earlier search of this position may already answer the question,
+        // in which case there is no need to generate its moves
+        let mut table_move = None;
+        if let Some(entry) = context.table.probe(self.hash) {
+            table_move = entry.best_move;
+            if ply > 0 && entry.depth >= depth {
+                let score = entry.score(ply);
+                match entry.bound {
+                    Bound::Exact => {
+                        pv.extend(entry.best_move);
+                        return score;
+                    }
+                    Bound::Lower => alpha = alpha.max(score),
+                    Bound::Upper => beta = beta.min(score),
+                }
+                if alpha >= beta {
+                    pv.extend(entry.best_move);
+                    return score;
+                }
+            }
+        }
+
+        let mut moves = self.legal_moves();
+        if moves.is_empty() {
+            return if self.is_check() {
+                -MATE + ply as i64 // prefer the quickest mate
+            } else {
+                0 // stalemate
+            };
+        }
+
+        let original_alpha = alpha;
+
+        // The best move from last time first, then captures and promotions
+        // as they're most likely to cause a cutoff
+        moves.sort_by_key(|mv| {
+            (
+                Some(*mv) != table_move,
+                !(mv.is_capture() || mv.promotion.is_some()),
+            )
+        });
+
+        let mut child_pv = Vec::new();
+        let mut best = -INFINITY;
+        for mv in moves {
+            self.make_move(mv);
+            let score = -self.negamax(depth - 1, ply + 1, -beta, -alpha, &mut child_pv, context);
+            self.unmake_move();
+
+            if context.aborted {
+                return 0;
+            }
+
+            if score > best {
+                best = score;
+                pv.clear();
+                pv.push(mv);
+                pv.extend_from_slice(&child_pv);
+            }
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        let bound = if best <= original_alpha {
+            Bound::Upper
+        } else if best >= beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        context
+            .table
+            .store(self.hash, depth, ply, bound, best, pv.first().copied());
+
+        best
+    }
+
+    /// Keep searching captures and promotions until the position is quiet,
+    /// so the static evaluation is never trusted in the middle of an exchange
+    /// https://www.chessprogramming.org/Quiescence_Search
+    fn quiescence(
+        &mut self,
+        ply: u32,
+        mut alpha: i64,
+        beta: i64,
+        context: &mut SearchContext,
+    ) -> i64 {
+        context.nodes += 1;
+        if context.should_stop() {
+            return 0;
+        }
+
+        // Every way out of check has to be looked at, standing still isn't an option
+        let in_check = self.is_check();
+        let mut moves = if in_check {
+            self.legal_moves()
+        } else {
+            self.capture_moves()
+        };
+        if in_check && moves.is_empty() {
+            return -MATE + ply as i64;
+        }
+
+        // Stand pat: assume some quiet move keeps at least the current score
+        let stand_pat = self.relative_evaluation(context.pawns);
+        if !in_check {
+            if stand_pat >= beta {
+                return stand_pat;
+            }
+            alpha = alpha.max(stand_pat);
+        }
+
+        // Most valuable victim first, taken by the least valuable attacker
+        moves.sort_by_key(|mv| {
+            (
+                -mv.captured.map_or(0, |p| piece_value(p.kind)),
+                piece_value(mv.piece.kind),
+            )
+        });
+
+        let mut best = if in_check { -INFINITY } else { stand_pat };
+        for mv in moves {
+            // Delta pruning: even winning this piece for free can't raise alpha
+            let gain = mv.captured.map_or(0, |p| piece_value(p.kind))
+                + mv.promotion
+                    .map_or(0, |p| piece_value(p) - piece_value(PieceKind::Pawn));
+            if !in_check && stand_pat + gain + DELTA_MARGIN < alpha {
+                continue;
+            }
+
+            self.make_move(mv);
+            let score = -self.quiescence(ply + 1, -beta, -alpha, context);
+            self.unmake_move();
+
+            if context.aborted {
+                return 0;
+            }
+
+            best = best.max(score);
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        best
+    }
+
+    /// Static evaluation from the side to move's point of view
+    fn relative_evaluation(&self, pawns: &mut PawnTable) -> i64 {
+        self.side_to_move.sign() * self.evaluate(pawns)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PawnWeights;
+
+    #[test]
+    fn test_mate_in_one() {
+        let fen = "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1";
+        let mut gs = GameState::from_string(fen).unwrap();
+        let result = gs.search(2);
+        assert_eq!(result.best_move.unwrap().to_string(), "a1a8");
+        assert_eq!(result.score, MATE - 1);
+        assert_eq!(gs.to_string(), fen, "Search did not restore the position");
+    }
+
+    #[test]
+    fn test_mate_at_leaf() {
+        // Only quiescence sees the position after Ra8, and has to find it's mate
+        let mut gs = GameState::from_string("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let result = gs.search(1);
+        assert_eq!(result.best_move.unwrap().to_string(), "a1a8");
+        assert_eq!(result.score, MATE - 1);
+    }
+
+    #[test]
+    fn test_mate_in_two() {
+        // Two rooks ladder the king up the board
+        let mut gs = GameState::from_string("7k/8/8/8/8/8/R7/1R4K1 w - - 0 1").unwrap();
+        let result = gs.search(3);
+        assert_eq!(result.score, MATE - 3);
+        assert_eq!(result.pv.len(), 3);
+    }
+
+    #[test]
+    fn test_wins_material() {
+        let mut gs = GameState::from_string(
+            "rnb1kbnr/pppp1ppp/8/4p1q1/3P4/8/PPP1PPPP/RNBQKBNR w KQkq - 0 1",
+        )
+        .unwrap();
+        let result = gs.search(2);
+        assert_eq!(result.best_move.unwrap().to_string(), "c1g5");
+        assert!(result.score > 0);
+    }
+
+    #[test]
+    fn test_iterative_deepening_reports() {
+        let mut gs = GameState::new();
+        let mut depths = Vec::new();
+        let result = gs.search_with_limits(
+            &SearchLimits::depth(3),
+            &mut TranspositionTable::default(),
+            &mut PawnTable::default(),
+            &AtomicBool::new(false),
+            |r| depths.push(r.depth),
+        );
+        assert_eq!(depths, vec![1, 2, 3]);
+        assert_eq!(result.depth, 3);
+        assert!(result.best_move.is_some());
+    }
+
+    #[test]
+    fn test_stop_flag() {
+        // Already stopped, only the first iteration is searched
+        let mut gs = GameState::new();
+        let result = gs.search_with_limits(
+            &SearchLimits::default(),
+            &mut TranspositionTable::default(),
+            &mut PawnTable::default(),
+            &AtomicBool::new(true),
+            |_| (),
+        );
+        assert_eq!(result.depth, 1);
+        assert!(result.best_move.is_some());
+        assert_eq!(gs.to_string(), GameState::new().to_string());
+    }
+
+    #[test]
+    fn test_node_limit() {
+        let mut gs = GameState::new();
+        let limits = SearchLimits {
+            nodes: Some(2000),
+            ..SearchLimits::default()
+        };
+        let result = gs.search_with_limits(
+            &limits,
+            &mut TranspositionTable::default(),
+            &mut PawnTable::default(),
+            &AtomicBool::new(false),
+            |_| (),
+        );
+        assert!(result.best_move.is_some());
+        assert!(result.nodes <= 2000);
+    }
+
+    #[test]
+    fn test_time_limit() {
+        let mut gs = GameState::from_string(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        )
+        .unwrap();
+        let limits = SearchLimits {
+            move_time: Some(Duration::from_millis(60)),
+            ..SearchLimits::default()
+        };
+        // Every look at the clock takes a millisecond, however fast the machine is
+        let mut now = Duration::ZERO;
+        let mut clock = || {
+            now += Duration::from_millis(1);
+            now
+        };
+        let result = gs.search_with_clock(
+            &limits,
+            &mut TranspositionTable::default(),
+            &mut PawnTable::default(),
+            &AtomicBool::new(false),
+            &mut clock,
+            |_| (),
+        );
+        assert!(result.best_move.is_some());
+        assert!(result.time < Duration::from_millis(60), "Ignored the clock");
+        assert!(result.nodes < 60 * CHECK_INTERVAL, "Ignored the clock");
+    }
+
+    #[test]
+    fn test_pawn_weights_used() {
+        let mut gs = GameState::from_string("4k3/8/8/3P4/8/8/8/4K3 w - - 0 1").unwrap();
+        let mut score = |pawns: &mut PawnTable| {
+            let limits = SearchLimits::depth(2);
+            let mut table = TranspositionTable::default();
+            let stop = AtomicBool::new(false);
+            gs.search_with_limits(&limits, &mut table, pawns, &stop, |_| ())
+                .score
+        };
+        let weights = PawnWeights {
+            passed: [(300, 300); 8],
+            ..PawnWeights::default()
+        };
+        let default = score(&mut PawnTable::default());
+        assert!(score(&mut PawnTable::new(1, weights)) > default + 200);
+    }
+
+    #[test]
+    fn test_table_reused() {
+        let mut gs = GameState::from_string(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        )
+        .unwrap();
+        let mut table = TranspositionTable::new(4);
+        let mut pawns = PawnTable::default();
+        let stop = AtomicBool::new(false);
+        let limits = SearchLimits::depth(3);
+
+        let first = gs.search_with_limits(&limits, &mut table, &mut pawns, &stop, |_| ());
+        let second = gs.search_with_limits(&limits, &mut table, &mut pawns, &stop, |_| ());
+        assert_eq!(first.best_move, second.best_move);
+        assert!(second.nodes < first.nodes, "Earlier results were not used");
+    }
+
+    #[test]
+    fn test_quiescence_sees_recapture() {
+        // Qxd5 wins a pawn at depth 1, but only until exd5
+        let mut gs = GameState::from_string("4k3/8/4p3/3p4/8/8/8/3QK3 w - - 0 1").unwrap();
+        let result = gs.search(1);
+        assert_ne!(result.best_move.unwrap().to_string(), "d1d5");
+        assert!(result.score > 500, "Queen against two pawns");
+    }
+
+    #[test]
+    fn test_quiescence_finishes_exchange() {
+        // Rxd5 is safe once the defending rook is taken off by the exchange
+        let mut gs = GameState::from_string("3rk3/8/8/3n4/8/8/3R4/3RK3 w - - 0 1").unwrap();
+        let result = gs.search(1);
+        assert_eq!(result.best_move.unwrap().to_string(), "d2d5");
+    }
+
+    #[test]
+    fn test_no_moves() {
+        let mut gs = GameState::from_string("4k3/4P3/4K3/8/8/8/8/8 b - - 0 1").unwrap();
+        let result = gs.search(3);
+        assert_eq!(result.best_move, None);
+        assert_eq!(result.score, 0);
+        assert!(result.pv.is_empty());
+    }
+}
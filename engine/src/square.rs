@@ -0,0 +1,119 @@
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use crate::position;
+use crate::{Bitboard, Error};
+
+/// One of the 64 squares, A1 = 0, H1 = 7, A8 = 56 through H8 = 63
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Square(u8);
+
+/// Name every square, `squares!(A1 = 0, ...)` makes `Square::A1`
+macro_rules! squares {
+    ($($name:ident = $index:expr),* $(,)?) => {
+        impl Square {
+            $(pub const $name: Square = Square($index);)*
+        }
+    };
+}
+
+squares! {
+    A1 = 0, B1 = 1, C1 = 2, D1 = 3, E1 = 4, F1 = 5, G1 = 6, H1 = 7,
+    A2 = 8, B2 = 9, C2 = 10, D2 = 11, E2 = 12, F2 = 13, G2 = 14, H2 = 15,
+    A3 = 16, B3 = 17, C3 = 18, D3 = 19, E3 = 20, F3 = 21, G3 = 22, H3 = 23,
+    A4 = 24, B4 = 25, C4 = 26, D4 = 27, E4 = 28, F4 = 29, G4 = 30, H4 = 31,
+    A5 = 32, B5 = 33, C5 = 34, D5 = 35, E5 = 36, F5 = 37, G5 = 38, H5 = 39,
+    A6 = 40, B6 = 41, C6 = 42, D6 = 43, E6 = 44, F6 = 45, G6 = 46, H6 = 47,
+    A7 = 48, B7 = 49, C7 = 50, D7 = 51, E7 = 52, F7 = 53, G7 = 54, H7 = 55,
+    A8 = 56, B8 = 57, C8 = 58, D8 = 59, E8 = 60, F8 = 61, G8 = 62, H8 = 63,
+}
+
+impl Square {
+    /// None if `index` is off the board
+    pub const fn new(index: u8) -> Option<Self> {
+        if index < 64 {
+            Some(Self(index))
+        } else {
+            None
+        }
+    }
+
+    /// For indices worked out from squares already on the board
+    pub(crate) const fn from_index(index: u8) -> Self {
+        assert!(index < 64, "Square index off the board");
+        Self(index)
+    }
+
+    /// Both counted from 0, so A1 is `(0, 0)` and H8 is `(7, 7)`
+    pub const fn from_rank_file(rank: u8, file: u8) -> Option<Self> {
+        if rank < 8 && file < 8 {
+            Some(Self(rank * 8 + file))
+        } else {
+            None
+        }
+    }
+
+    /// Every square, A1 first
+    pub fn all() -> impl Iterator<Item = Square> {
+        (0..64).map(Square)
+    }
+
+    pub const fn index(self) -> u8 {
+        self.0
+    }
+
+    /// 0 for the first rank through 7 for the eighth
+    pub const fn rank(self) -> u8 {
+        self.0 / 8
+    }
+
+    /// 0 for the A file through 7 for the H file
+    pub const fn file(self) -> u8 {
+        self.0 % 8
+    }
+
+    pub const fn bitboard(self) -> Bitboard {
+        Bitboard(1 << self.0)
+    }
+}
+
+impl Display for Square {
+    /// Lowercase coordinate, ex: `e4`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            position::square_to_string(*self).to_ascii_lowercase()
+        )
+    }
+}
+
+impl FromStr for Square {
+    type Err = Error;
+
+    fn from_str(coord: &str) -> Result<Self, Self::Err> {
+        position::string_to_square(coord)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_square() {
+        assert_eq!(Square::new(28), Some(Square::E4));
+        assert_eq!(Square::new(64), None);
+        assert_eq!(Square::from_rank_file(3, 4), Some(Square::E4));
+        assert_eq!(Square::from_rank_file(8, 0), None);
+        assert_eq!((Square::E4.rank(), Square::E4.file()), (3, 4));
+        assert_eq!(Square::all().count(), 64);
+    }
+
+    #[test]
+    fn test_square_strings() {
+        assert_eq!(Square::H8.to_string(), "h8");
+        assert_eq!("E4".parse(), Ok(Square::E4));
+        assert!("e9".parse::<Square>().is_err());
+    }
+}
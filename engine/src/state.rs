@@ -1,94 +1,105 @@
 use super::Chessboard;
+use crate::{Bitboard, Color, Piece, PieceKind, Square};
+
 impl Chessboard {
-    pub fn piece_bitboards(&self) -> [(char, u64); 12] {
+    pub fn piece_bitboards(&self) -> [(Piece, Bitboard); 12] {
+        use Color::{Black, White};
+        use PieceKind::*;
         [
-            ('P', self.white_pawns),
-            ('N', self.white_knights),
-            ('B', self.white_bishops),
-            ('K', self.white_king),
-            ('Q', self.white_queen),
-            ('R', self.white_rooks),
-            ('p', self.black_pawns),
-            ('n', self.black_knights),
-            ('b', self.black_bishops),
-            ('k', self.black_king),
-            ('q', self.black_queen),
-            ('r', self.black_rooks),
+            (Piece::new(White, Pawn), self.white_pawns),
+            (Piece::new(White, Knight), self.white_knights),
+            (Piece::new(White, Bishop), self.white_bishops),
+            (Piece::new(White, King), self.white_king),
+            (Piece::new(White, Queen), self.white_queen),
+            (Piece::new(White, Rook), self.white_rooks),
+            (Piece::new(Black, Pawn), self.black_pawns),
+            (Piece::new(Black, Knight), self.black_knights),
+            (Piece::new(Black, Bishop), self.black_bishops),
+            (Piece::new(Black, King), self.black_king),
+            (Piece::new(Black, Queen), self.black_queen),
+            (Piece::new(Black, Rook), self.black_rooks),
         ]
     }
 
-    pub fn piece_bitboard(&mut self, piece: char) -> Result<&mut u64, String> {
-        match piece {
-            'p' => Ok(&mut self.black_pawns),
-            'r' => Ok(&mut self.black_rooks),
-            'n' => Ok(&mut self.black_knights),
-            'b' => Ok(&mut self.black_bishops),
-            'k' => Ok(&mut self.black_king),
-            'q' => Ok(&mut self.black_queen),
-            'P' => Ok(&mut self.white_pawns),
-            'R' => Ok(&mut self.white_rooks),
-            'N' => Ok(&mut self.white_knights),
-            'B' => Ok(&mut self.white_bishops),
-            'K' => Ok(&mut self.white_king),
-            'Q' => Ok(&mut self.white_queen),
-            _ => Err(format!("Invalid piece type: {piece}")),
+    pub fn piece_bitboard(&mut self, piece: Piece) -> &mut Bitboard {
+        match (piece.color, piece.kind) {
+            (Color::Black, PieceKind::Pawn) => &mut self.black_pawns,
+            (Color::Black, PieceKind::Rook) => &mut self.black_rooks,
+            (Color::Black, PieceKind::Knight) => &mut self.black_knights,
+            (Color::Black, PieceKind::Bishop) => &mut self.black_bishops,
+            (Color::Black, PieceKind::King) => &mut self.black_king,
+            (Color::Black, PieceKind::Queen) => &mut self.black_queen,
+            (Color::White, PieceKind::Pawn) => &mut self.white_pawns,
+            (Color::White, PieceKind::Rook) => &mut self.white_rooks,
+            (Color::White, PieceKind::Knight) => &mut self.white_knights,
+            (Color::White, PieceKind::Bishop) => &mut self.white_bishops,
+            (Color::White, PieceKind::King) => &mut self.white_king,
+            (Color::White, PieceKind::Queen) => &mut self.white_queen,
+        }
+    }
+
+    /// Where every piece of this colour and kind stands
+    pub fn pieces(&self, piece: Piece) -> Bitboard {
+        match (piece.color, piece.kind) {
+            (Color::Black, PieceKind::Pawn) => self.black_pawns,
+            (Color::Black, PieceKind::Rook) => self.black_rooks,
+            (Color::Black, PieceKind::Knight) => self.black_knights,
+            (Color::Black, PieceKind::Bishop) => self.black_bishops,
+            (Color::Black, PieceKind::King) => self.black_king,
+            (Color::Black, PieceKind::Queen) => self.black_queen,
+            (Color::White, PieceKind::Pawn) => self.white_pawns,
+            (Color::White, PieceKind::Rook) => self.white_rooks,
+            (Color::White, PieceKind::Knight) => self.white_knights,
+            (Color::White, PieceKind::Bishop) => self.white_bishops,
+            (Color::White, PieceKind::King) => self.white_king,
+            (Color::White, PieceKind::Queen) => self.white_queen,
         }
     }
 
-    pub fn piece_at_position(&self, square: u8) -> Option<char> {
-        let btwise = 1 << square;
-        for (p_type, positions) in self.piece_bitboards() {
-            if btwise & positions != 0 {
-                return Some(p_type);
+    pub fn piece_at_position(&self, square: Square) -> Option<Piece> {
+        for (piece, positions) in self.piece_bitboards() {
+            if positions.contains(square) {
+                return Some(piece);
             }
         }
         None
     }
 
-    pub fn one_side_pieces(&self, white: bool) -> u64 {
-        if white {
-            self.white_bishops
-                | self.white_king
-                | self.white_knights
-                | self.white_pawns
-                | self.white_rooks
-                | self.white_queen
-        } else {
-            self.black_bishops
-                | self.black_king
-                | self.black_knights
-                | self.black_pawns
-                | self.black_rooks
-                | self.black_queen
+    /// Remove whatever piece is on this square
+    pub fn remove_piece(&mut self, square: Square) {
+        if let Some(piece) = self.piece_at_position(square) {
+            *self.piece_bitboard(piece) &= !square.bitboard();
         }
     }
 
-    pub fn both_side_pieces(&self) -> u64 {
-        self.one_side_pieces(true) | self.one_side_pieces(false)
+    /// Place a piece on this square, it is up to the caller to make sure it is empty
+    pub fn put_piece(&mut self, square: Square, piece: Piece) {
+        *self.piece_bitboard(piece) |= square.bitboard();
     }
 
-    /// Determine who is winning
-    /// A positive number indicates white is winning
-    pub fn evaluate(&self) -> i64 {
-        let mut result = 0;
-
-        for (piece, board) in self.piece_bitboards() {
-            let score = match piece.to_ascii_uppercase() {
-                'P' => 1,
-                'R' => 5,
-                'N' => 3,
-                'B' => 3,
-                'Q' => 9,
-                _ => 0,
-            } * board.count_ones() as i64;
-            if piece.is_ascii_uppercase() {
-                result += score;
-            } else {
-                result -= score;
+    pub fn one_side_pieces(&self, color: Color) -> Bitboard {
+        match color {
+            Color::White => {
+                self.white_bishops
+                    | self.white_king
+                    | self.white_knights
+                    | self.white_pawns
+                    | self.white_rooks
+                    | self.white_queen
+            }
+            Color::Black => {
+                self.black_bishops
+                    | self.black_king
+                    | self.black_knights
+                    | self.black_pawns
+                    | self.black_rooks
+                    | self.black_queen
             }
         }
+    }
 
-        result
+    pub fn both_side_pieces(&self) -> Bitboard {
+        self.one_side_pieces(Color::White) | self.one_side_pieces(Color::Black)
     }
 }
 
@@ -99,19 +110,33 @@ mod tests {
     #[test]
     fn test_piece_at_position() {
         let cb = Chessboard {
-            white_pawns: 0x0100,
-            black_knights: 0x00400000,
+            white_pawns: Bitboard(0x0100),
+            black_knights: Bitboard(0x00400000),
             ..Chessboard::empty()
         };
 
-        assert_eq!(cb.piece_at_position(8), Some('P'));
-        assert_eq!(cb.piece_at_position(22), Some('n'));
-        assert_eq!(cb.piece_at_position(32), None);
+        assert_eq!(cb.piece_at_position(Square::A2), Piece::from_char('P'));
+        assert_eq!(cb.piece_at_position(Square::G3), Piece::from_char('n'));
+        assert_eq!(cb.piece_at_position(Square::A5), None);
+    }
+
+    #[test]
+    fn test_remove_piece() {
+        let mut cb = Chessboard::new();
+        cb.remove_piece(Square::A2);
+        assert_eq!(cb.piece_at_position(Square::A2), None);
+        assert_eq!(cb.white_pawns, Bitboard(0xFE00));
+
+        cb.remove_piece(Square::A5); // nothing here
+        assert_eq!(cb.white_pawns, Bitboard(0xFE00));
     }
 
     #[test]
-    fn test_evaluate() {
-        let gs = Chessboard::new();
-        assert_eq!(gs.evaluate(), 0);
+    fn test_put_piece() {
+        let mut cb = Chessboard::empty();
+        let knight = Piece::new(Color::Black, PieceKind::Knight);
+        cb.put_piece(Square::G3, knight);
+        assert_eq!(cb.piece_at_position(Square::G3), Some(knight));
+        assert_eq!(cb.pieces(knight), Square::G3.bitboard());
     }
 }
@@ -0,0 +1,223 @@
+use std::mem;
+
+use crate::search::MATE;
+use crate::Move;
+
+// https://www.chessprogramming.org/Transposition_Table
+
+/// Size used when the GUI doesn't ask for one
+pub const DEFAULT_HASH_MB: usize = 16;
+/// Largest size the front ends will allocate, GUIs can ask for more than the machine has
+pub const MAX_HASH_MB: usize = 4096;
+/// Scores this close to `MATE` are mates, stored relative to the node rather than the root
+const MATE_THRESHOLD: i64 = MATE - 1000;
+
+/// How a stored score relates to the true score of the position
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Bound {
+    Exact,
+    Lower, // failed high, the true score is at least this
+    Upper, // failed low, the true score is at most this
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Entry {
+    key: u64, // full Zobrist key, the index only uses part of it
+    pub(crate) depth: u32,
+    pub(crate) bound: Bound,
+    score: i64,
+    pub(crate) best_move: Option<Move>,
+    generation: u8, // which search stored it
+}
+
+impl Entry {
+    /// Score as seen from `ply` plies below the root
+    pub(crate) fn score(&self, ply: u32) -> i64 {
+        match self.score {
+            s if s > MATE_THRESHOLD => s - ply as i64,
+            s if s < -MATE_THRESHOLD => s + ply as i64,
+            s => s,
+        }
+    }
+}
+
+/// Fixed size table of search results keyed by position
+/// Always replaces entries left over from earlier searches, otherwise prefers deeper results
+pub struct TranspositionTable {
+    entries: Vec<Option<Entry>>,
+    generation: u8,
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        Self::new(DEFAULT_HASH_MB)
+    }
+}
+
+impl TranspositionTable {
+    /// A table using about `megabytes` of memory, with room for at least one entry
+    pub fn new(megabytes: usize) -> Self {
+        let count = (megabytes * 1024 * 1024 / mem::size_of::<Option<Entry>>()).max(1);
+        Self {
+            entries: vec![None; count],
+            generation: 0,
+        }
+    }
+
+    /// Change the size, throwing away everything stored
+    pub fn resize(&mut self, megabytes: usize) {
+        *self = Self::new(megabytes);
+    }
+
+    /// Forget every stored position, e.g. when a new game starts
+    pub fn clear(&mut self) {
+        self.entries.fill(None);
+        self.generation = 0;
+    }
+
+    /// Number of entries the table can hold
+    pub fn capacity(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Permille of the table filled by the current search, as UCI `hashfull` expects
+    pub fn hashfull(&self) -> usize {
+        let sample = self.entries.len().min(1000);
+        let used = self.entries[..sample]
+            .iter()
+            .flatten()
+            .filter(|e| e.generation == self.generation)
+            .count();
+        used * 1000 / sample
+    }
+
+    /// Called before every search so older entries can be told apart
+    pub(crate) fn new_search(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+    }
+
+    fn index(&self, key: u64) -> usize {
+        (key % self.entries.len() as u64) as usize
+    }
+
+    pub(crate) fn probe(&self, key: u64) -> Option<&Entry> {
+        self.entries[self.index(key)]
+            .as_ref()
+            .filter(|e| e.key == key)
+    }
+
+    /// Remember the result of searching a position `ply` plies below the root
+    pub(crate) fn store(
+        &mut self,
+        key: u64,
+        depth: u32,
+        ply: u32,
+        bound: Bound,
+        score: i64,
+        best_move: Option<Move>,
+    ) {
+        let generation = self.generation;
+        let index = self.index(key);
+        let slot = &mut self.entries[index];
+
+        let replace = match slot {
+            None => true,
+            Some(old) => old.key == key || old.generation != generation || depth >= old.depth,
+        };
+        if !replace {
+            return;
+        }
+
+        // Keep the old move if this search didn't find one for the same position
+        let best_move = match slot {
+            Some(old) if old.key == key => best_move.or(old.best_move),
+            _ => best_move,
+        };
+
+        let score = match score {
+            s if s > MATE_THRESHOLD => s + ply as i64,
+            s if s < -MATE_THRESHOLD => s - ply as i64,
+            s => s,
+        };
+
+        *slot = Some(Entry {
+            key,
+            depth,
+            bound,
+            score,
+            best_move,
+            generation,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::GameState;
+
+    #[test]
+    fn test_store_and_probe() {
+        let mut gs = GameState::new();
+        let mv = gs.legal_moves()[0];
+        let mut table = TranspositionTable::new(1);
+
+        assert!(table.probe(gs.hash()).is_none());
+        table.store(gs.hash(), 3, 0, Bound::Exact, 25, Some(mv));
+
+        let entry = table.probe(gs.hash()).expect("Just stored");
+        assert_eq!(entry.depth, 3);
+        assert_eq!(entry.bound, Bound::Exact);
+        assert_eq!(entry.score(0), 25);
+        assert_eq!(entry.best_move, Some(mv));
+
+        gs.make_move(mv);
+        assert!(table.probe(gs.hash()).is_none());
+
+        table.clear();
+        gs.unmake_move();
+        assert!(table.probe(gs.hash()).is_none());
+    }
+
+    #[test]
+    fn test_mate_scores_relative_to_node() {
+        let mut table = TranspositionTable::new(1);
+        // Mate 5 plies from the root, found 2 plies in, is mate in 3 from that node
+        table.store(42, 4, 2, Bound::Exact, MATE - 5, None);
+        let entry = table.probe(42).unwrap();
+        assert_eq!(entry.score(2), MATE - 5);
+        assert_eq!(entry.score(4), MATE - 7, "Reached by a longer path");
+    }
+
+    #[test]
+    fn test_replacement() {
+        let mut table = TranspositionTable::new(1);
+        let size = table.capacity() as u64;
+        let (a, b) = (7, 7 + size); // same slot
+
+        table.new_search();
+        table.store(a, 5, 0, Bound::Exact, 1, None);
+        table.store(b, 2, 0, Bound::Lower, 2, None);
+        assert!(
+            table.probe(a).is_some(),
+            "Shallower result replaced a deeper one"
+        );
+        assert!(table.probe(b).is_none());
+
+        table.new_search();
+        table.store(b, 2, 0, Bound::Lower, 2, None);
+        assert!(
+            table.probe(b).is_some(),
+            "Entry from an old search was kept"
+        );
+        assert!(table.probe(a).is_none());
+    }
+
+    #[test]
+    fn test_size() {
+        let table = TranspositionTable::new(1);
+        let bytes = table.capacity() * mem::size_of::<Option<Entry>>();
+        assert!(bytes <= 1024 * 1024 && bytes > 1000 * 1024);
+        assert_eq!(TranspositionTable::new(0).capacity(), 1);
+    }
+}
@@ -0,0 +1,221 @@
+use crate::{CastlingRights, Color, GameState, Piece, PieceKind, Square};
+
+// https://www.chessprogramming.org/Zobrist_Hashing
+
+/// Random numbers xor'd together to make a position's key
+struct Keys {
+    pieces: [[u64; 64]; 12], // white's pieces then black's, each in `PieceKind` order
+    black_to_move: u64,
+    castling: [u64; 16],  // one per combination of KQkq
+    en_passant: [u64; 8], // one per file
+}
+
+/// Generated at compile time so keys are the same on every run
+const KEYS: Keys = generate_keys(0x4B6E_4F33_C4E5_5EED);
+
+/// https://prng.di.unimi.it/splitmix64.c
+pub(crate) const fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+const fn generate_keys(seed: u64) -> Keys {
+    let mut state = seed;
+    let mut keys = Keys {
+        pieces: [[0; 64]; 12],
+        black_to_move: 0,
+        castling: [0; 16],
+        en_passant: [0; 8],
+    };
+
+    let mut piece = 0;
+    while piece < 12 {
+        let mut square = 0;
+        while square < 64 {
+            keys.pieces[piece][square] = splitmix64(&mut state);
+            square += 1;
+        }
+        piece += 1;
+    }
+
+    keys.black_to_move = splitmix64(&mut state);
+
+    // No rights keeps key 0 so an empty board with white to move hashes to 0
+    let mut rights = 1;
+    while rights < 16 {
+        keys.castling[rights] = splitmix64(&mut state);
+        rights += 1;
+    }
+
+    let mut file = 0;
+    while file < 8 {
+        keys.en_passant[file] = splitmix64(&mut state);
+        file += 1;
+    }
+
+    keys
+}
+
+/// Key for `piece` standing on `square`
+pub(crate) fn piece_key(piece: Piece, square: Square) -> u64 {
+    let index = piece.color as usize * 6 + piece.kind as usize;
+    KEYS.pieces[index][square.index() as usize]
+}
+
+pub(crate) fn castling_key(castling: CastlingRights) -> u64 {
+    KEYS.castling[castling.index()]
+}
+
+/// Only the file matters, nothing is added when there is no en passant square
+pub(crate) fn en_passant_key(en_passant: Option<Square>) -> u64 {
+    en_passant.map_or(0, |square| KEYS.en_passant[square.file() as usize])
+}
+
+pub(crate) fn side_key() -> u64 {
+    KEYS.black_to_move
+}
+
+impl GameState {
+    /// 64 bit Zobrist key of the position
+    /// Kept up to date by `make_move` and `unmake_move`
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// Key of the pawns alone, for caching pawn structure
+    pub fn pawn_hash(&self) -> u64 {
+        self.pawn_hash
+    }
+
+    /// Add or remove a piece from the keys
+    pub(crate) fn toggle_piece_key(&mut self, piece: Piece, square: Square) {
+        let key = piece_key(piece, square);
+        self.hash ^= key;
+        if piece.kind == PieceKind::Pawn {
+            self.pawn_hash ^= key;
+        }
+    }
+
+    /// Build the key from scratch rather than incrementally
+    pub(crate) fn compute_hash(&self) -> u64 {
+        let mut hash = castling_key(self.castling) ^ en_passant_key(self.en_passant);
+        if !self.side_to_move.is_white() {
+            hash ^= side_key();
+        }
+
+        for (piece, board) in self.board.piece_bitboards() {
+            for square in board {
+                hash ^= piece_key(piece, square);
+            }
+        }
+        hash
+    }
+
+    pub(crate) fn compute_pawn_hash(&self) -> u64 {
+        let mut hash = 0;
+        for color in [Color::White, Color::Black] {
+            let pawn = Piece::new(color, PieceKind::Pawn);
+            for square in self.board.pieces(pawn) {
+                hash ^= piece_key(pawn, square);
+            }
+        }
+        hash
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Play every legal move `depth` plies deep, checking the incremental key each time
+    fn check_incremental(gs: &mut GameState, depth: u32) {
+        assert_eq!(gs.hash(), gs.compute_hash(), "{gs}");
+        assert_eq!(gs.pawn_hash(), gs.compute_pawn_hash(), "{gs}");
+        if depth == 0 {
+            return;
+        }
+        for mv in gs.legal_moves() {
+            let before = gs.hash();
+            gs.make_move(mv);
+            check_incremental(gs, depth - 1);
+            gs.unmake_move();
+            assert_eq!(gs.hash(), before, "{mv} was not undone");
+        }
+    }
+
+    #[test]
+    fn test_incremental_hash() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/1P6/8/3pP3/8/8/6p1/R3K2R w KQkq d6 0 1",
+        ] {
+            check_incremental(&mut GameState::from_string(fen).unwrap(), 2);
+        }
+    }
+
+    #[test]
+    fn test_transposition() {
+        let play = |moves: [(Square, Square); 4]| {
+            let mut gs = GameState::new();
+            for (from, to) in moves {
+                gs.move_piece_legally(from, to, None).unwrap();
+            }
+            gs
+        };
+
+        // 1. Nf3 Nf6 2. Nc3 Nc6 and 1. Nc3 Nc6 2. Nf3 Nf6
+        let a = play([
+            (Square::G1, Square::F3),
+            (Square::G8, Square::F6),
+            (Square::B1, Square::C3),
+            (Square::B8, Square::C6),
+        ]);
+        let b = play([
+            (Square::B1, Square::C3),
+            (Square::B8, Square::C6),
+            (Square::G1, Square::F3),
+            (Square::G8, Square::F6),
+        ]);
+        assert_eq!(a.hash(), b.hash());
+        assert_ne!(a.hash(), GameState::new().hash());
+    }
+
+    #[test]
+    fn test_state_changes_hash() {
+        let hash = |fen| GameState::from_string(fen).unwrap().hash();
+        let start = hash("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+
+        assert_ne!(
+            start,
+            hash("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 0 1")
+        );
+        assert_ne!(
+            start,
+            hash("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w Kkq - 0 1")
+        );
+        assert_ne!(
+            start,
+            hash("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e3 0 1")
+        );
+        // Only pawns change the pawn key
+        let pawn_hash = |fen| GameState::from_string(fen).unwrap().pawn_hash();
+        assert_eq!(
+            pawn_hash("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"),
+            pawn_hash("4k3/pppppppp/8/8/8/8/PPPPPPPP/4K3 b - - 0 1")
+        );
+        assert_ne!(
+            pawn_hash("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"),
+            pawn_hash("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1")
+        );
+
+        // The clocks aren't part of the position
+        assert_eq!(
+            start,
+            hash("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 7 20")
+        );
+    }
+}